@@ -0,0 +1,332 @@
+//! Whole-ROM static analysis, as used by `term-8 disasm`.
+//!
+//! Code is found by recursive descent from 0x200: every reachable
+//! instruction is decoded once, following jumps, calls, both outcomes of
+//! skips and the `JP` entries of `BNNN` jump tables. Bytes that are never
+//! reached are treated as data, and the targets of jumps, calls and `LD I`
+//! are given labels.
+
+use crate::disassembler;
+use crate::symbols::Symbols;
+use std::collections::BTreeMap;
+
+/// Where ROMs are loaded and execution starts.
+pub const ORIGIN: usize = 0x200;
+
+/// Entries followed in a `BNNN` jump table.
+const MAX_TABLE_ENTRIES: usize = 64;
+
+/// Data bytes per `DB` line, outside of sprites.
+const DATA_ROW: usize = 8;
+
+/// What refers to a labelled address. When an address is referred to in
+/// several ways, the first kind listed wins.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Reference {
+    /// Called with `2NNN`.
+    Call,
+    /// Jumped to with `1NNN` or `BNNN`.
+    Jump,
+    /// Loaded into I, usually a sprite.
+    Data,
+}
+
+#[derive(Clone, Debug)]
+pub struct Analysis {
+    /// The ROM, at its load address.
+    memory: Vec<u8>,
+
+    /// Start address and length of every reachable instruction.
+    instructions: BTreeMap<usize, usize>,
+
+    /// Addresses inside the ROM that instructions refer to.
+    references: BTreeMap<usize, Reference>,
+}
+
+impl Analysis {
+    pub fn new(rom: &[u8]) -> Self {
+        let mut memory = vec![0; ORIGIN];
+        memory.extend_from_slice(rom);
+        let mut analysis = Self {
+            memory,
+            instructions: BTreeMap::new(),
+            references: BTreeMap::new(),
+        };
+
+        let mut pending = vec![ORIGIN];
+        while let Some(addr) = pending.pop() {
+            let Some(len) = analysis.instruction_len(addr) else {
+                continue;
+            };
+            if (addr..addr + len).any(|byte| analysis.is_code(byte)) {
+                continue;
+            }
+            analysis.instructions.insert(addr, len);
+
+            let opcode = analysis.word(addr).unwrap_or(0);
+            let nnn = (opcode & 0x0FFF) as usize;
+            let next = addr + len;
+            match opcode & 0xF000 {
+                0x0000 if matches!(opcode, 0x00EE | 0x00FD) => {}
+                0x1000 => {
+                    analysis.refer(nnn, Reference::Jump);
+                    pending.push(nnn);
+                }
+                0x2000 => {
+                    analysis.refer(nnn, Reference::Call);
+                    pending.extend([next, nnn]);
+                }
+                0xB000 => {
+                    // Usually a table of JP instructions indexed by V0.
+                    analysis.refer(nnn, Reference::Jump);
+                    pending.push(nnn);
+                    for entry in (1..MAX_TABLE_ENTRIES).map(|k| nnn + k * 2) {
+                        if !matches!(analysis.word(entry), Some(word) if word & 0xF000 == 0x1000) {
+                            break;
+                        }
+                        pending.push(entry);
+                    }
+                }
+                0xA000 => {
+                    analysis.refer(nnn, Reference::Data);
+                    pending.push(next);
+                }
+                0xF000 if opcode == 0xF000 => {
+                    let target = analysis.word(addr + 2).unwrap_or(0);
+                    analysis.refer(target as usize, Reference::Data);
+                    pending.push(next);
+                }
+                _ if is_skip(opcode) => {
+                    let skipped = next + analysis.instruction_len(next).unwrap_or(2);
+                    pending.extend([skipped, next]);
+                }
+                _ => pending.push(next),
+            }
+        }
+
+        analysis
+    }
+
+    fn word(&self, addr: usize) -> Option<u16> {
+        Some((*self.memory.get(addr)? as u16) << 8 | *self.memory.get(addr + 1)? as u16)
+    }
+
+    /// Length of the instruction at `addr`, or `None` if it is not a valid
+    /// instruction inside the ROM.
+    fn instruction_len(&self, addr: usize) -> Option<usize> {
+        if addr < ORIGIN {
+            return None;
+        }
+        let opcode = self.word(addr)?;
+        let text = disassembler::disassemble(opcode);
+        if text.starts_with("DATA") || text.starts_with("SYS") {
+            return None;
+        }
+        let len = if opcode == 0xF000 { 4 } else { 2 };
+        (addr + len <= self.memory.len()).then_some(len)
+    }
+
+    fn refer(&mut self, addr: usize, reference: Reference) {
+        if (ORIGIN..self.memory.len()).contains(&addr) {
+            let kind = self.references.entry(addr).or_insert(reference);
+            *kind = (*kind).min(reference);
+        }
+    }
+
+    /// Whether `addr` is part of a reachable instruction.
+    pub fn is_code(&self, addr: usize) -> bool {
+        self.instructions
+            .range(..=addr)
+            .next_back()
+            .is_some_and(|(&start, &len)| addr < start + len)
+    }
+
+    /// Reachable instructions as (address, length), in address order.
+    pub fn instructions(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.instructions.iter().map(|(&addr, &len)| (addr, len))
+    }
+
+    pub fn code_bytes(&self) -> usize {
+        self.instructions.values().sum()
+    }
+
+    /// The label for `addr`: its name in `symbols`, or one generated from
+    /// how it is referred to, e.g. `sub_2A4`, `loc_206` or `data_2EA`.
+    pub fn label(&self, addr: usize, symbols: &Symbols) -> Option<String> {
+        if let Some(name) = symbols.name(addr as u16) {
+            return Some(name.to_string());
+        }
+        let prefix = match self.references.get(&addr)? {
+            Reference::Call => "sub",
+            Reference::Jump => "loc",
+            Reference::Data => "data",
+        };
+        Some(format!("{}_{:03X}", prefix, addr))
+    }
+
+    /// An assembly listing of the whole ROM. Each line is commented with its
+    /// address and bytes, and sprite data with a picture of each row.
+    pub fn listing(&self, symbols: &Symbols) -> String {
+        // Addresses inside an instruction get no line of their own to
+        // carry a label, so they are written as numbers.
+        let label = |addr: u16| {
+            let addr = addr as usize;
+            let inside = self.is_code(addr) && !self.instructions.contains_key(&addr);
+            if inside { None } else { self.label(addr, symbols) }
+        };
+        let mut lines = Vec::new();
+        let mut addr = ORIGIN;
+
+        while addr < self.memory.len() {
+            if let Some(name) = label(addr as u16) {
+                if !lines.is_empty() {
+                    lines.push(String::new());
+                }
+                lines.push(format!("{}:", name));
+            }
+
+            if let Some(&len) = self.instructions.get(&addr) {
+                let (text, _) = disassembler::disassemble_at_labeled(&self.memory, addr, &label);
+                let bytes = hex_bytes(&self.memory[addr..addr + len], "");
+                lines.push(format!("    {:<28}; {:04X}  {}", text, addr, bytes));
+                addr += len;
+                continue;
+            }
+
+            // Data runs until the next instruction or label.
+            let end = (addr + 1..self.memory.len())
+                .find(|&at| self.instructions.contains_key(&at) || label(at as u16).is_some())
+                .unwrap_or(self.memory.len());
+            let sprite = self.references.get(&addr) == Some(&Reference::Data);
+
+            if sprite {
+                for (at, &byte) in (addr..end).zip(&self.memory[addr..end]) {
+                    let row: String = (0..8).rev().map(|bit| if byte >> bit & 1 == 1 { '█' } else { '.' }).collect();
+                    lines.push(format!("    {:<28}; {:04X}  {}", format!("DB   {:02X}", byte), at, row));
+                }
+            } else {
+                for start in (addr..end).step_by(DATA_ROW) {
+                    let bytes = &self.memory[start..(start + DATA_ROW).min(end)];
+                    lines.push(format!("    {:<28}; {:04X}", format!("DB   {}", hex_bytes(bytes, ", ")), start));
+                }
+            }
+            addr = end;
+        }
+
+        lines.push(String::new());
+        lines.join("\n")
+    }
+}
+
+/// `3XNN`, `4XNN`, `5XY0`, `9XY0`, `EX9E` and `EXA1`.
+fn is_skip(opcode: u16) -> bool {
+    match opcode & 0xF000 {
+        0x3000 | 0x4000 => true,
+        0x5000 | 0x9000 => opcode & 0x000F == 0,
+        0xE000 => matches!(opcode & 0x00FF, 0x9E | 0xA1),
+        _ => false,
+    }
+}
+
+fn hex_bytes(bytes: &[u8], separator: &str) -> String {
+    bytes.iter().map(|byte| format!("{:02X}", byte)).collect::<Vec<_>>().join(separator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM: [u8; 16] = [
+        0x22, 0x0A, // CALL 0x20A
+        0x30, 0x00, // SE V0, 00
+        0x12, 0x02, // JP 0x202
+        0xA2, 0x0C, // LD I, 0x20C
+        0x12, 0x08, // JP 0x208
+        0x00, 0xEE, // RET
+        0xF0, 0x90, // sprite, never executed
+        0x60, 0x01, // decodes as LD V0, 01 but is never reached either
+    ];
+
+    fn addresses(analysis: &Analysis) -> Vec<usize> {
+        analysis.instructions().map(|(addr, _)| addr).collect()
+    }
+
+    #[test]
+    fn code_is_what_calls_jumps_and_skips_reach() {
+        let analysis = Analysis::new(&PROGRAM);
+        assert_eq!(addresses(&analysis), [0x200, 0x202, 0x204, 0x206, 0x208, 0x20A]);
+        assert_eq!(analysis.code_bytes(), 12);
+        assert!(analysis.is_code(0x20B));
+        assert!(!analysis.is_code(0x20C) && !analysis.is_code(0x20E));
+    }
+
+    #[test]
+    fn labels_say_how_addresses_are_used() {
+        let analysis = Analysis::new(&PROGRAM);
+        let symbols = Symbols::default();
+        let label = |addr| analysis.label(addr, &symbols);
+        assert_eq!(label(0x20A).as_deref(), Some("sub_20A"));
+        assert_eq!(label(0x202).as_deref(), Some("loc_202"));
+        assert_eq!(label(0x208).as_deref(), Some("loc_208"));
+        assert_eq!(label(0x20C).as_deref(), Some("data_20C"));
+        assert_eq!(label(0x206), None);
+
+        let symbols = Symbols::parse(":draw 0x20A").unwrap();
+        assert_eq!(analysis.label(0x20A, &symbols).as_deref(), Some("draw"));
+    }
+
+    #[test]
+    fn jump_tables_are_followed() {
+        let analysis = Analysis::new(&[
+            0xB2, 0x04, // JP V0, 0x204
+            0x00, 0x00, // never reached
+            0x12, 0x08, // JP 0x208
+            0x12, 0x0A, // JP 0x20A
+            0x12, 0x08, // JP 0x208
+            0x12, 0x0A, // JP 0x20A
+            0xFF, 0xFF, // not a jump, so the end of the table
+        ]);
+        assert_eq!(addresses(&analysis), [0x200, 0x204, 0x206, 0x208, 0x20A]);
+    }
+
+    #[test]
+    fn long_loads_are_four_bytes_and_skipped_whole() {
+        let analysis = Analysis::new(&[
+            0x30, 0x00, // SE V0, 00
+            0xF0, 0x00, 0x02, 0x08, // LD I, 0x0208
+            0x12, 0x06, // JP 0x206
+            0xF0, 0x90, // sprite
+        ]);
+        assert_eq!(analysis.instructions().collect::<Vec<_>>(), [(0x200, 2), (0x202, 4), (0x206, 2)]);
+        assert_eq!(analysis.label(0x208, &Symbols::default()).as_deref(), Some("data_208"));
+    }
+
+    #[test]
+    fn listing_labels_code_and_draws_sprites() {
+        let symbols = Symbols::parse(":draw 0x20A").unwrap();
+        let listing = Analysis::new(&PROGRAM).listing(&symbols);
+        let lines: Vec<&str> = listing.lines().collect();
+        assert!(lines.contains(&"draw:"));
+        assert!(lines.contains(&"data_20C:"));
+        let has = |text: &str, comment: &str| {
+            lines.iter().any(|line| line.starts_with(text) && line.ends_with(comment))
+        };
+        assert!(has("    CALL draw ", "; 0200  220A"));
+        assert!(has("    DB   F0 ", "; 020C  ████...."));
+        assert!(has("    DB   60 ", "; 020E  .██....."));
+    }
+
+    #[test]
+    fn bundled_roms_separate_code_from_sprites() {
+        let ibm = Analysis::new(include_bytes!("../roms/ibm_logo.ch8"));
+        assert_eq!((ibm.code_bytes(), ibm.instructions().count()), (42, 21));
+        assert!(ibm.is_code(0x228) && !ibm.is_code(0x22A));
+
+        for rom in [&include_bytes!("../roms/pong.ch8")[..], include_bytes!("../roms/tetris.ch8")] {
+            let analysis = Analysis::new(rom);
+            assert!(analysis.is_code(ORIGIN));
+            assert!(analysis.code_bytes() < rom.len());
+            assert!(analysis.instructions().all(|(addr, len)| addr + len <= ORIGIN + rom.len()));
+        }
+    }
+}
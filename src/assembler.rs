@@ -0,0 +1,612 @@
+//! A two-pass assembler for the Cowgod-style syntax that the
+//! [`disassembler`](crate::disassembler) writes, so listings from
+//! `term-8 disasm` assemble back to the same bytes.
+//!
+//! ```text
+//! SPEED EQU 2            ; constants: NAME EQU value, or NAME = value
+//! start:
+//!     LD   VA, SPEED
+//!     LD   I, paddle
+//!     DRW  VA, VB, 6
+//!     JP   start
+//! paddle:
+//!     DB   80, 80, 80, 80, 80, 80
+//! ```
+//!
+//! Mnemonics and registers are case-insensitive, names are not. Numbers are
+//! hexadecimal as in the disassembly; `0x`, `#` and `$` prefixes are
+//! accepted too, and `%` for binary. An operand can add and subtract
+//! labels, constants and numbers, e.g. `sprites+5`. `;` starts a comment.
+
+use crate::analysis::ORIGIN;
+use crate::symbols::is_label;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Constants referring to constants are followed this many levels deep.
+const MAX_DEPTH: usize = 16;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AsmError {
+    /// One-based line number.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+/// One source line, split up.
+struct Line<'a> {
+    number: usize,
+    label: Option<&'a str>,
+    constant: Option<(&'a str, &'a str)>,
+    mnemonic: String,
+    operands: Vec<&'a str>,
+}
+
+impl<'a> Line<'a> {
+    fn parse(number: usize, text: &'a str) -> Self {
+        let mut text = text.split(';').next().unwrap_or("").trim();
+        let mut line = Self {
+            number,
+            label: None,
+            constant: None,
+            mnemonic: String::new(),
+            operands: Vec::new(),
+        };
+
+        let first = text.split_whitespace().next().unwrap_or("");
+        if let Some(label) = first.strip_suffix(':') {
+            line.label = Some(label);
+            text = text[first.len()..].trim_start();
+        }
+
+        let (mnemonic, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let rest = rest.trim();
+        if let Some(value) = rest.strip_prefix('=') {
+            line.constant = Some((mnemonic, value.trim()));
+            return line;
+        }
+        if let Some((keyword, value)) = rest.split_once(char::is_whitespace) {
+            if keyword.eq_ignore_ascii_case("EQU") {
+                line.constant = Some((mnemonic, value.trim()));
+                return line;
+            }
+        }
+
+        line.mnemonic = mnemonic.to_ascii_uppercase();
+        if !rest.is_empty() {
+            line.operands = rest.split(',').map(str::trim).collect();
+        }
+        line
+    }
+
+    /// Bytes the line assembles to, which must be known before labels are.
+    fn size(&self) -> usize {
+        match (self.mnemonic.as_str(), &self.operands[..]) {
+            ("", _) => 0,
+            ("DB", operands) => operands.len(),
+            ("DW" | "DATA", operands) => operands.len() * 2,
+            ("LD", [_, source]) if long_operand(source).is_some_and(|target| !target.is_empty()) => 4,
+            _ => 2,
+        }
+    }
+}
+
+/// Labels and constants defined in a source file.
+#[derive(Default)]
+struct Names<'a> {
+    labels: HashMap<&'a str, i64>,
+    constants: HashMap<&'a str, &'a str>,
+}
+
+impl Names<'_> {
+    fn define(&mut self, name: &str) -> Result<(), String> {
+        if !is_label(name) {
+            return Err(format!("'{}' is not a valid name", name));
+        }
+        if self.labels.contains_key(name) || self.constants.contains_key(name) {
+            return Err(format!("'{}' is already defined", name));
+        }
+        Ok(())
+    }
+
+    fn value(&self, name: &str, depth: usize) -> Result<Option<i64>, String> {
+        if let Some(&addr) = self.labels.get(name) {
+            return Ok(Some(addr));
+        }
+        let Some(expr) = self.constants.get(name) else {
+            return Ok(None);
+        };
+        if depth == MAX_DEPTH {
+            return Err(format!("constant '{}' refers to itself", name));
+        }
+        eval(expr, &|inner| self.value(inner, depth + 1)).map(Some)
+    }
+}
+
+/// Assembles `source` into a ROM image to be loaded at 0x200. On failure,
+/// returns an error for every line that could not be assembled.
+pub fn assemble(source: &str) -> Result<Vec<u8>, Vec<AsmError>> {
+    let lines: Vec<Line> = source
+        .lines()
+        .enumerate()
+        .map(|(index, text)| Line::parse(index + 1, text))
+        .collect();
+    let mut names = Names::default();
+    let mut errors = Vec::new();
+
+    // First pass: where every label ends up.
+    let mut addr = ORIGIN;
+    for line in &lines {
+        let error = |message| AsmError { line: line.number, message };
+        if let Some(label) = line.label {
+            match names.define(label) {
+                Ok(()) => {
+                    names.labels.insert(label, addr as i64);
+                }
+                Err(message) => errors.push(error(message)),
+            }
+        }
+        if let Some((name, expr)) = line.constant {
+            match names.define(name) {
+                Ok(()) => {
+                    names.constants.insert(name, expr);
+                }
+                Err(message) => errors.push(error(message)),
+            }
+        }
+        addr += line.size();
+    }
+    if addr > 0x10000 {
+        errors.push(AsmError {
+            line: lines.len(),
+            message: format!("program is {} bytes, too large to fit in memory", addr - ORIGIN),
+        });
+    }
+
+    // Second pass: the bytes.
+    let mut rom = Vec::with_capacity(addr - ORIGIN);
+    for line in lines.iter().filter(|line| !line.mnemonic.is_empty()) {
+        let resolve = |name: &str| names.value(name, 0);
+        match encode(&line.mnemonic, &line.operands, &resolve) {
+            Ok(bytes) => rom.extend(bytes),
+            Err(message) => {
+                errors.push(AsmError { line: line.number, message });
+                rom.extend(vec![0; line.size()]);
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(rom)
+    } else {
+        errors.sort_by_key(|error| error.line);
+        Err(errors)
+    }
+}
+
+/// Assembles a single instruction such as `LD VA, 05`, looking up any
+/// names in it with `resolve`.
+pub fn assemble_instruction(text: &str, resolve: &dyn Fn(&str) -> Option<u16>) -> Result<Vec<u8>, String> {
+    let line = Line::parse(1, text);
+    if line.label.is_some() || line.constant.is_some() {
+        return Err("expected an instruction".to_string());
+    }
+    if line.mnemonic.is_empty() {
+        return Err("nothing to assemble".to_string());
+    }
+    encode(&line.mnemonic, &line.operands, &|name| Ok(resolve(name).map(i64::from)))
+}
+
+/// An operand as written, before any value in it is evaluated. `F` and `B`
+/// are also hex digits, so they stay values and `LD` checks for them.
+enum Operand<'a> {
+    V(u16),
+    Range(u16, u16),
+    I,
+    IndirectI,
+    Dt,
+    St,
+    K,
+    Hf,
+    R,
+    Long(&'a str),
+    Value(&'a str),
+}
+
+impl<'a> Operand<'a> {
+    fn parse(text: &'a str) -> Self {
+        if let Some(target) = long_operand(text) {
+            return Operand::Long(target);
+        }
+        if let Some((first, last)) = text.split_once('-') {
+            if let (Some(x), Some(y)) = (register(first.trim()), register(last.trim())) {
+                return Operand::Range(x, y);
+            }
+        }
+        if let Some(x) = register(text) {
+            return Operand::V(x);
+        }
+        match text.to_ascii_uppercase().as_str() {
+            "I" => Operand::I,
+            "[I]" => Operand::IndirectI,
+            "DT" => Operand::Dt,
+            "ST" => Operand::St,
+            "K" => Operand::K,
+            "HF" => Operand::Hf,
+            "R" => Operand::R,
+            _ => Operand::Value(text),
+        }
+    }
+}
+
+/// `V0`-`VF`.
+fn register(text: &str) -> Option<u16> {
+    let index = text.strip_prefix(['V', 'v'])?;
+    if index.len() != 1 {
+        return None;
+    }
+    u16::from_str_radix(index, 16).ok()
+}
+
+/// The target of `LONG target`, possibly empty.
+fn long_operand(text: &str) -> Option<&str> {
+    let keyword = text.get(..4)?;
+    let rest = &text[4..];
+    (keyword.eq_ignore_ascii_case("LONG") && (rest.is_empty() || rest.starts_with(char::is_whitespace)))
+        .then(|| rest.trim())
+}
+
+fn encode(
+    mnemonic: &str,
+    operands: &[&str],
+    resolve: &dyn Fn(&str) -> Result<Option<i64>, String>,
+) -> Result<Vec<u8>, String> {
+    use Operand::*;
+
+    let value = |text: &str, max: i64| -> Result<u16, String> {
+        let value = eval(text, resolve)?;
+        if !(0..=max).contains(&value) {
+            return Err(format!("{} is out of range (0 to {:X})", text, max));
+        }
+        Ok(value as u16)
+    };
+    let addr = |text: &str| value(text, 0xFFF);
+    let nibble = |text: &str| value(text, 0xF);
+    // Bytes may also be written as negative numbers, e.g. `ADD V0, -1`.
+    let byte = |text: &str| -> Result<u16, String> {
+        let value = eval(text, resolve)?;
+        if !(-0x80..=0xFF).contains(&value) {
+            return Err(format!("{} is out of range for a byte", text));
+        }
+        Ok(value as u8 as u16)
+    };
+
+    let parsed: Vec<Operand> = operands.iter().map(|text| Operand::parse(text)).collect();
+    let opcode = match (mnemonic, &parsed[..]) {
+        ("DB", values) if !values.is_empty() => {
+            return operands.iter().map(|text| byte(text).map(|b| b as u8)).collect();
+        }
+        ("DW" | "DATA", values) if !values.is_empty() => {
+            let mut bytes = Vec::with_capacity(operands.len() * 2);
+            for text in operands {
+                bytes.extend(value(text, 0xFFFF)?.to_be_bytes());
+            }
+            return Ok(bytes);
+        }
+        ("LD", [I, Long(target)]) => {
+            if target.is_empty() {
+                return Ok(vec![0xF0, 0x00]);
+            }
+            let [high, low] = value(target, 0xFFFF)?.to_be_bytes();
+            return Ok(vec![0xF0, 0x00, high, low]);
+        }
+
+        ("CLS", []) => 0x00E0,
+        ("RET", []) => 0x00EE,
+        ("SCD", [Value(n)]) => 0x00C0 | nibble(n)?,
+        ("SCU", [Value(n)]) => 0x00D0 | nibble(n)?,
+        ("SCR", []) => 0x00FB,
+        ("SCL", []) => 0x00FC,
+        ("EXIT", []) => 0x00FD,
+        ("LOW", []) => 0x00FE,
+        ("HIGH", []) => 0x00FF,
+        ("SYS", [Value(a)]) => addr(a)?,
+        ("JP", [Value(a)]) => 0x1000 | addr(a)?,
+        ("JP", [V(0), Value(a)]) => 0xB000 | addr(a)?,
+        ("CALL", [Value(a)]) => 0x2000 | addr(a)?,
+        ("SE", [V(x), V(y)]) => 0x5000 | x << 8 | y << 4,
+        ("SE", [V(x), Value(b)]) => 0x3000 | x << 8 | byte(b)?,
+        ("SNE", [V(x), V(y)]) => 0x9000 | x << 8 | y << 4,
+        ("SNE", [V(x), Value(b)]) => 0x4000 | x << 8 | byte(b)?,
+
+        ("LD", [V(x), V(y)]) => 0x8000 | x << 8 | y << 4,
+        ("LD", [V(x), Dt]) => 0xF007 | x << 8,
+        ("LD", [V(x), K]) => 0xF00A | x << 8,
+        ("LD", [V(x), IndirectI]) => 0xF065 | x << 8,
+        ("LD", [V(x), R]) => 0xF085 | x << 8,
+        ("LD", [V(x), Value(b)]) => 0x6000 | x << 8 | byte(b)?,
+        ("LD", [I, Value(a)]) => 0xA000 | addr(a)?,
+        ("LD", [Dt, V(x)]) => 0xF015 | x << 8,
+        ("LD", [St, V(x)]) => 0xF018 | x << 8,
+        ("LD", [Value(f), V(x)]) if f.eq_ignore_ascii_case("F") => 0xF029 | x << 8,
+        ("LD", [Hf, V(x)]) => 0xF030 | x << 8,
+        ("LD", [Value(b), V(x)]) if b.eq_ignore_ascii_case("B") => 0xF033 | x << 8,
+        ("LD", [IndirectI, V(x)]) => 0xF055 | x << 8,
+        ("LD", [R, V(x)]) => 0xF075 | x << 8,
+        ("LD", [IndirectI, Range(x, y)]) => 0x5002 | x << 8 | y << 4,
+        ("LD", [Range(x, y), IndirectI]) => 0x5003 | x << 8 | y << 4,
+
+        ("ADD", [V(x), V(y)]) => 0x8004 | x << 8 | y << 4,
+        ("ADD", [V(x), Value(b)]) => 0x7000 | x << 8 | byte(b)?,
+        ("ADD", [I, V(x)]) => 0xF01E | x << 8,
+        ("OR", [V(x), V(y)]) => 0x8001 | x << 8 | y << 4,
+        ("AND", [V(x), V(y)]) => 0x8002 | x << 8 | y << 4,
+        ("XOR", [V(x), V(y)]) => 0x8003 | x << 8 | y << 4,
+        ("SUB", [V(x), V(y)]) => 0x8005 | x << 8 | y << 4,
+        ("SUBN", [V(x), V(y)]) => 0x8007 | x << 8 | y << 4,
+        ("SHR", [V(x)]) => 0x8006 | x << 8,
+        ("SHR", [V(x), V(y)]) => 0x8006 | x << 8 | y << 4,
+        ("SHL", [V(x)]) => 0x800E | x << 8,
+        ("SHL", [V(x), V(y)]) => 0x800E | x << 8 | y << 4,
+        ("RND", [V(x), Value(b)]) => 0xC000 | x << 8 | byte(b)?,
+        ("DRW", [V(x), V(y), Value(n)]) => 0xD000 | x << 8 | y << 4 | nibble(n)?,
+        ("SKP", [V(x)]) => 0xE09E | x << 8,
+        ("SKNP", [V(x)]) => 0xE0A1 | x << 8,
+
+        ("PLANE", [Value(n)]) => 0xF001 | nibble(n)? << 8,
+        ("AUDIO", []) => 0xF002,
+        ("PITCH", [V(x)]) => 0xF03A | x << 8,
+
+        (
+            "CLS" | "RET" | "SCD" | "SCU" | "SCR" | "SCL" | "EXIT" | "LOW" | "HIGH" | "SYS" | "JP" | "CALL" | "SE"
+            | "SNE" | "LD" | "ADD" | "OR" | "AND" | "XOR" | "SUB" | "SUBN" | "SHR" | "SHL" | "RND" | "DRW" | "SKP"
+            | "SKNP" | "PLANE" | "AUDIO" | "PITCH" | "DB" | "DW" | "DATA",
+            _,
+        ) => return Err(format!("invalid operands for {}: '{}'", mnemonic, operands.join(", "))),
+        _ => return Err(format!("unknown instruction '{}'", mnemonic)),
+    };
+
+    Ok(opcode.to_be_bytes().to_vec())
+}
+
+/// Evaluates an operand such as `sprites+5`, looking names up with
+/// `resolve`.
+fn eval(text: &str, resolve: &dyn Fn(&str) -> Result<Option<i64>, String>) -> Result<i64, String> {
+    let text = text.trim();
+    // Names may contain dashes, so try the whole operand as one first.
+    if let Some(value) = resolve(text)? {
+        return Ok(value);
+    }
+
+    let mut total = 0;
+    let mut rest = text;
+    let mut sign = 1;
+    loop {
+        rest = rest.trim_start();
+        if let Some(after) = rest.strip_prefix('-') {
+            sign = -sign;
+            rest = after;
+            continue;
+        }
+        let end = rest.find(['+', '-']).unwrap_or(rest.len());
+        let atom = rest[..end].trim();
+        if atom.is_empty() {
+            return Err(format!("expected a value in '{}'", text));
+        }
+        total += sign * atom_value(atom, resolve)?;
+
+        rest = &rest[end..];
+        match rest.strip_prefix('+') {
+            Some(after) => rest = after,
+            None if rest.is_empty() => return Ok(total),
+            None => {}
+        }
+        sign = 1;
+    }
+}
+
+fn atom_value(atom: &str, resolve: &dyn Fn(&str) -> Result<Option<i64>, String>) -> Result<i64, String> {
+    if let Some(value) = resolve(atom)? {
+        return Ok(value);
+    }
+    let lower = atom.to_ascii_lowercase();
+    let (digits, radix) = if let Some(binary) = lower.strip_prefix('%') {
+        (binary, 2)
+    } else {
+        let hex = lower.strip_prefix("0x").or_else(|| lower.strip_prefix(['#', '$']));
+        (hex.unwrap_or(&lower), 16)
+    };
+    match i64::from_str_radix(digits, radix) {
+        Ok(value) if !digits.starts_with(['+', '-']) => Ok(value),
+        _ if is_label(atom) => Err(format!("'{}' is not defined", atom)),
+        _ => Err(format!("'{}' is not a number", atom)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::Analysis;
+    use crate::disassembler;
+    use crate::symbols::Symbols;
+
+    fn line(text: &str) -> Vec<u8> {
+        assemble(&format!("    {}\n", text)).unwrap_or_else(|errors| panic!("{}: {:?}", text, errors))
+    }
+
+    fn errors(source: &str) -> Vec<(usize, String)> {
+        assemble(source)
+            .unwrap_err()
+            .into_iter()
+            .map(|error| (error.line, error.message))
+            .collect()
+    }
+
+    #[test]
+    fn every_opcode_round_trips() {
+        for opcode in 0..=0xFFFFu16 {
+            let bytes = opcode.to_be_bytes().to_vec();
+            let (text, _) = disassembler::disassemble_at(&bytes, 0);
+            assert_eq!(line(&text), bytes, "{:04X} disassembles to '{}'", opcode, text);
+        }
+    }
+
+    #[test]
+    fn long_load_round_trips() {
+        let bytes = [0xF0, 0x00, 0x12, 0x34];
+        let (text, len) = disassembler::disassemble_at(&bytes, 0);
+        assert_eq!(len, 4);
+        assert_eq!(line(&text), bytes);
+    }
+
+    #[test]
+    fn each_form_assembles() {
+        let forms: &[(&str, &[u8])] = &[
+            ("CLS", &[0x00, 0xE0]),
+            ("RET", &[0x00, 0xEE]),
+            ("SCD 4", &[0x00, 0xC4]),
+            ("SCU 2", &[0x00, 0xD2]),
+            ("SCR", &[0x00, 0xFB]),
+            ("SCL", &[0x00, 0xFC]),
+            ("EXIT", &[0x00, 0xFD]),
+            ("LOW", &[0x00, 0xFE]),
+            ("HIGH", &[0x00, 0xFF]),
+            ("SYS 123", &[0x01, 0x23]),
+            ("JP 2A4", &[0x12, 0xA4]),
+            ("CALL 300", &[0x23, 0x00]),
+            ("SE V3, 0x10", &[0x33, 0x10]),
+            ("SNE V3, 10", &[0x43, 0x10]),
+            ("SE V1, V2", &[0x51, 0x20]),
+            ("LD [I], V1-V4", &[0x51, 0x42]),
+            ("LD V4-V1, [I]", &[0x54, 0x13]),
+            ("LD VA, #FF", &[0x6A, 0xFF]),
+            ("ADD V0, $7F", &[0x70, 0x7F]),
+            ("LD V1, V2", &[0x81, 0x20]),
+            ("OR V1, V2", &[0x81, 0x21]),
+            ("AND V1, V2", &[0x81, 0x22]),
+            ("XOR V1, V2", &[0x81, 0x23]),
+            ("ADD V1, V2", &[0x81, 0x24]),
+            ("SUB V1, V2", &[0x81, 0x25]),
+            ("SHR V1", &[0x81, 0x06]),
+            ("SHR V1, V2", &[0x81, 0x26]),
+            ("SUBN V1, V2", &[0x81, 0x27]),
+            ("SHL V1", &[0x81, 0x0E]),
+            ("SNE V1, V2", &[0x91, 0x20]),
+            ("LD I, 2EA", &[0xA2, 0xEA]),
+            ("JP V0, 300", &[0xB3, 0x00]),
+            ("RND V5, %1111", &[0xC5, 0x0F]),
+            ("DRW VA, VB, 6", &[0xDA, 0xB6]),
+            ("SKP V7", &[0xE7, 0x9E]),
+            ("SKNP V7", &[0xE7, 0xA1]),
+            ("LD I, LONG 1234", &[0xF0, 0x00, 0x12, 0x34]),
+            ("PLANE 3", &[0xF3, 0x01]),
+            ("AUDIO", &[0xF0, 0x02]),
+            ("LD V2, DT", &[0xF2, 0x07]),
+            ("LD V2, K", &[0xF2, 0x0A]),
+            ("LD DT, V2", &[0xF2, 0x15]),
+            ("LD ST, V2", &[0xF2, 0x18]),
+            ("ADD I, V2", &[0xF2, 0x1E]),
+            ("LD F, V2", &[0xF2, 0x29]),
+            ("LD HF, V2", &[0xF2, 0x30]),
+            ("LD B, V2", &[0xF2, 0x33]),
+            ("PITCH V2", &[0xF2, 0x3A]),
+            ("LD [I], V2", &[0xF2, 0x55]),
+            ("LD V2, [I]", &[0xF2, 0x65]),
+            ("LD R, V2", &[0xF2, 0x75]),
+            ("LD V2, R", &[0xF2, 0x85]),
+            ("DATA FFFF", &[0xFF, 0xFF]),
+            ("DB 1, 2, 3", &[0x01, 0x02, 0x03]),
+            ("DW 1234, 5", &[0x12, 0x34, 0x00, 0x05]),
+            ("ld vb, vf", &[0x8B, 0xF0]),
+        ];
+        for (text, bytes) in forms {
+            assert_eq!(&line(text), bytes, "{}", text);
+        }
+    }
+
+    #[test]
+    fn labels_and_constants_resolve() {
+        let source = "\
+SPEED EQU 2
+TOP = sprite+1
+start:
+    LD   VA, SPEED
+    LD   I, sprite
+    LD   I, TOP
+    CALL sub
+    JP   start
+sub:
+    RET
+sprite:
+    DB   80, 80
+";
+        let rom = assemble(source).unwrap();
+        assert_eq!(
+            rom,
+            [0x6A, 0x02, 0xA2, 0x0C, 0xA2, 0x0D, 0x22, 0x0A, 0x12, 0x00, 0x00, 0xEE, 0x80, 0x80]
+        );
+    }
+
+    #[test]
+    fn long_loads_resolve_forward_labels() {
+        let rom = assemble("    LD I, LONG data\n    JP 200\ndata:\n    DB 1\n").unwrap();
+        assert_eq!(rom, [0xF0, 0x00, 0x02, 0x06, 0x12, 0x00, 0x01]);
+    }
+
+    #[test]
+    fn errors_are_reported_by_line() {
+        let source = "\
+start:
+    LD   V0, 100
+    JP   nowhere
+start:
+    FOO  V1
+    SE   V1, V2, V3
+";
+        assert_eq!(
+            errors(source),
+            [
+                (2, "100 is out of range for a byte".to_string()),
+                (3, "'nowhere' is not defined".to_string()),
+                (4, "'start' is already defined".to_string()),
+                (5, "unknown instruction 'FOO'".to_string()),
+                (6, "invalid operands for SE: 'V1, V2, V3'".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn self_referential_constants_are_errors() {
+        assert_eq!(errors("A = B\nB = A\n    LD V0, A\n"), [(3, "constant 'A' refers to itself".to_string())]);
+    }
+
+    #[test]
+    fn single_instructions_use_the_resolver() {
+        let resolve = |name: &str| (name == "loop").then_some(0x2A4);
+        assert_eq!(assemble_instruction("JP loop", &resolve), Ok(vec![0x12, 0xA4]));
+        assert_eq!(assemble_instruction("JP other", &resolve), Err("'other' is not defined".to_string()));
+        assert_eq!(assemble_instruction("here:", &resolve), Err("expected an instruction".to_string()));
+        assert_eq!(assemble_instruction("  ; comment", &resolve), Err("nothing to assemble".to_string()));
+    }
+
+    #[test]
+    fn bundled_rom_listings_reassemble() {
+        let roms: [&[u8]; 3] = [
+            include_bytes!("../roms/pong.ch8"),
+            include_bytes!("../roms/tetris.ch8"),
+            include_bytes!("../roms/ibm_logo.ch8"),
+        ];
+        for rom in roms {
+            let listing = Analysis::new(rom).listing(&Symbols::default());
+            assert_eq!(assemble(&listing).unwrap(), rom);
+        }
+    }
+}
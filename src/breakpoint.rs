@@ -0,0 +1,51 @@
+use crate::expr::{Expr, ParseError};
+
+/// A PC breakpoint, optionally guarded by a condition and a hit count.
+#[derive(Clone, Debug, Default)]
+pub struct Breakpoint {
+    /// Set for breakpoints named in the program, like Octo's `:breakpoint`.
+    pub name: Option<String>,
+
+    pub condition: Option<Condition>,
+
+    /// Only stop on every this many hits.
+    pub hit_target: Option<u32>,
+
+    /// Times PC reached the breakpoint with its condition true; with a hit
+    /// target, since it last stopped.
+    pub hits: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct Condition {
+    /// The expression as typed, for display.
+    pub source: String,
+    pub expr: Expr,
+}
+
+impl Condition {
+    pub fn parse(source: &str) -> Result<Self, ParseError> {
+        Ok(Self {
+            source: source.trim().to_string(),
+            expr: Expr::parse(source)?,
+        })
+    }
+}
+
+impl Breakpoint {
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(name) = &self.name {
+            parts.push(name.clone());
+        }
+        if let Some(condition) = &self.condition {
+            parts.push(format!("if {}", condition.source));
+        }
+        match self.hit_target {
+            Some(target) => parts.push(format!("hits {}/{}", self.hits, target)),
+            None if self.hits > 0 => parts.push(format!("hits {}", self.hits)),
+            None => {}
+        }
+        parts.join(", ")
+    }
+}
@@ -0,0 +1,298 @@
+//! Per-ROM settings from a local copy of the community CHIP-8 database
+//! (`programs.json` from https://github.com/chip-8/chip-8-database), looked
+//! up by the SHA-1 of the ROM.
+
+use crate::quirks::{Platform, Quirks};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+/// What the database knows about one ROM.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RomInfo {
+    pub title: String,
+    pub authors: Vec<String>,
+
+    /// The first of the ROM's platforms that term-8 can emulate.
+    pub platform: Option<Platform>,
+
+    /// The platform's quirks, with any the ROM needs changed.
+    pub quirks: Option<Quirks>,
+
+    /// Recommended instructions per frame.
+    pub cycles_per_frame: Option<u32>,
+
+    /// Background first, then one colour per combination of bitplanes.
+    pub colors: Vec<(u8, u8, u8)>,
+
+    /// What each keypad key does, e.g. `("up", 0x1)`, in keypad order.
+    pub keys: Vec<(String, u8)>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Database {
+    roms: HashMap<String, RomInfo>,
+}
+
+#[derive(Deserialize)]
+struct Program {
+    title: String,
+    #[serde(default)]
+    authors: Vec<String>,
+    #[serde(default)]
+    roms: HashMap<String, Rom>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Rom {
+    #[serde(default)]
+    platforms: Vec<String>,
+    tickrate: Option<u32>,
+    #[serde(default)]
+    keys: BTreeMap<String, u8>,
+    colors: Option<Colors>,
+    #[serde(default)]
+    quirky_platforms: HashMap<String, QuirkOverrides>,
+}
+
+#[derive(Deserialize)]
+struct Colors {
+    #[serde(default)]
+    pixels: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QuirkOverrides {
+    shift: Option<bool>,
+    memory_increment_by_x: Option<bool>,
+    memory_leave_i_unchanged: Option<bool>,
+    wrap: Option<bool>,
+    jump: Option<bool>,
+    vblank: Option<bool>,
+    logic: Option<bool>,
+}
+
+impl Database {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        Self::parse(&text).map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    /// Parses the contents of a `programs.json`.
+    pub fn parse(json: &str) -> Result<Self, serde_json::Error> {
+        let programs: Vec<Program> = serde_json::from_str(json)?;
+        let mut roms = HashMap::new();
+        for program in programs {
+            for (sha1, rom) in program.roms {
+                let info = rom_info(&program.title, &program.authors, rom);
+                roms.insert(sha1.to_ascii_lowercase(), info);
+            }
+        }
+        Ok(Self { roms })
+    }
+
+    /// Looks up a ROM by its hex SHA-1, as from [`crate::Emulator::rom_sha1`].
+    pub fn get(&self, sha1: &str) -> Option<&RomInfo> {
+        self.roms.get(&sha1.to_ascii_lowercase())
+    }
+
+    pub fn len(&self) -> usize {
+        self.roms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roms.is_empty()
+    }
+}
+
+fn rom_info(title: &str, authors: &[String], rom: Rom) -> RomInfo {
+    let platform = rom
+        .platforms
+        .iter()
+        .find_map(|id| platform_quirks(id).map(|(platform, quirks)| (id, platform, quirks)));
+    let quirks = platform.map(|(id, _, mut quirks)| {
+        if let Some(overrides) = rom.quirky_platforms.get(id) {
+            overrides.apply(&mut quirks);
+        }
+        quirks
+    });
+
+    let mut keys: Vec<(String, u8)> = rom.keys.into_iter().filter(|&(_, key)| key < 16).collect();
+    keys.sort_by_key(|&(_, key)| key);
+
+    RomInfo {
+        title: title.to_string(),
+        authors: authors.to_vec(),
+        platform: platform.map(|(_, platform, _)| platform),
+        quirks,
+        cycles_per_frame: rom.tickrate.filter(|&tickrate| tickrate > 0),
+        colors: rom
+            .colors
+            .map(|colors| colors.pixels.iter().filter_map(|color| parse_color(color)).collect())
+            .unwrap_or_default(),
+        keys,
+    }
+}
+
+/// The closest emulated platform to a database platform id, and that
+/// platform's quirks.
+fn platform_quirks(id: &str) -> Option<(Platform, Quirks)> {
+    let platform = match id {
+        "originalChip8" | "hybridVIP" => Platform::Chip8,
+        "modernChip8" => Platform::ModernChip8,
+        "chip48" => Platform::Chip48,
+        "superchip1" | "superchip" => Platform::SuperChip,
+        "xochip" => Platform::XoChip,
+        _ => return None,
+    };
+    Some((platform, platform.quirks()))
+}
+
+impl QuirkOverrides {
+    fn apply(&self, quirks: &mut Quirks) {
+        if let Some(shift) = self.shift {
+            quirks.shift = shift;
+        }
+        if self.memory_increment_by_x.is_some() || self.memory_leave_i_unchanged.is_some() {
+            let by_x = self.memory_increment_by_x.unwrap_or(false);
+            let unchanged = self.memory_leave_i_unchanged.unwrap_or(false);
+            quirks.memory_increment = !by_x && !unchanged;
+            quirks.memory_increment_by_x = by_x && !unchanged;
+        }
+        if let Some(wrap) = self.wrap {
+            quirks.clip = !wrap;
+        }
+        if let Some(jump) = self.jump {
+            quirks.jump_vx = jump;
+        }
+        if let Some(vblank) = self.vblank {
+            quirks.display_wait = vblank;
+        }
+        if let Some(logic) = self.logic {
+            quirks.vf_reset = logic;
+        }
+    }
+}
+
+/// Parses `#RRGGBB` or `#RGB`.
+fn parse_color(text: &str) -> Option<(u8, u8, u8)> {
+    let hex = text.strip_prefix('#')?;
+    let digits: Vec<u8> = hex
+        .chars()
+        .map(|c| c.to_digit(16).map(|digit| digit as u8))
+        .collect::<Option<_>>()?;
+    match digits[..] {
+        [r, g, b] => Some((r * 17, g * 17, b * 17)),
+        [r1, r2, g1, g2, b1, b2] => Some((r1 << 4 | r2, g1 << 4 | g2, b1 << 4 | b2)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAMS: &str = r##"[
+        {
+            "title": "Pong",
+            "authors": ["Paul Vervalin"],
+            "roms": {
+                "AbC123": {
+                    "platforms": ["superchip8", "originalChip8"],
+                    "tickrate": 15,
+                    "keys": {"up": 1, "down": 4, "bogus": 16},
+                    "colors": {"pixels": ["#000000", "#f80", "red"]},
+                    "quirkyPlatforms": {
+                        "originalChip8": {"shift": true, "vblank": false}
+                    }
+                }
+            }
+        },
+        {
+            "title": "Chip-48 Game",
+            "roms": {
+                "def456": {
+                    "platforms": ["chip48"],
+                    "tickrate": 0,
+                    "quirkyPlatforms": {"chip48": {"memoryIncrementByX": true}}
+                },
+                "fed789": {
+                    "platforms": ["modernChip8"],
+                    "quirkyPlatforms": {"modernChip8": {"memoryLeaveIUnchanged": true}}
+                }
+            }
+        }
+    ]"##;
+
+    fn database() -> Database {
+        Database::parse(PROGRAMS).unwrap()
+    }
+
+    #[test]
+    fn roms_are_found_by_sha1_in_any_case() {
+        let database = database();
+        assert_eq!(database.len(), 3);
+        assert_eq!(database.get("abc123").unwrap().title, "Pong");
+        assert_eq!(database.get("ABC123").unwrap().authors, ["Paul Vervalin"]);
+        assert!(database.get("000000").is_none());
+    }
+
+    #[test]
+    fn the_first_known_platform_is_used_with_its_overrides() {
+        let info = database().get("abc123").unwrap().clone();
+        assert_eq!(info.platform, Some(Platform::Chip8));
+        let quirks = info.quirks.unwrap();
+        assert!(quirks.shift);
+        assert!(!quirks.display_wait);
+        assert_eq!(
+            Quirks {
+                shift: false,
+                display_wait: true,
+                ..quirks
+            },
+            Platform::Chip8.quirks()
+        );
+    }
+
+    #[test]
+    fn memory_quirk_overrides() {
+        let database = database();
+        let chip48 = database.get("def456").unwrap().quirks.unwrap();
+        assert!(!chip48.memory_increment);
+        assert!(chip48.memory_increment_by_x);
+
+        let modern = database.get("fed789").unwrap();
+        assert_eq!(modern.platform, Some(Platform::ModernChip8));
+        let quirks = modern.quirks.unwrap();
+        assert!(!quirks.memory_increment);
+        assert!(!quirks.memory_increment_by_x);
+    }
+
+    #[test]
+    fn tickrate_keys_and_colors() {
+        let database = database();
+        let pong = database.get("abc123").unwrap();
+        assert_eq!(pong.cycles_per_frame, Some(15));
+        assert_eq!(pong.keys, [("up".to_string(), 1), ("down".to_string(), 4)]);
+        assert_eq!(pong.colors, [(0, 0, 0), (0xFF, 0x88, 0)]);
+        assert_eq!(database.get("def456").unwrap().cycles_per_frame, None);
+    }
+
+    #[test]
+    fn colors_parse() {
+        assert_eq!(parse_color("#12aBEf"), Some((0x12, 0xAB, 0xEF)));
+        assert_eq!(parse_color("#fff"), Some((0xFF, 0xFF, 0xFF)));
+        assert_eq!(parse_color("#12345"), None);
+        assert_eq!(parse_color("#ggg"), None);
+        assert_eq!(parse_color("fff"), None);
+    }
+
+    #[test]
+    fn malformed_json_is_an_error() {
+        assert!(Database::parse("{").is_err());
+        assert!(Database::parse(r#"[{"roms": {}}]"#).is_err());
+    }
+}
@@ -9,13 +9,25 @@ pub fn disassemble(opcode: u16) -> String {
         0x0000 => match opcode {
             0x00E0 => "CLS".to_string(),
             0x00EE => "RET".to_string(),
+            0x00C0..=0x00CF => format!("SCD  {:X}", n),
+            0x00D0..=0x00DF => format!("SCU  {:X}", n),
+            0x00FB => "SCR".to_string(),
+            0x00FC => "SCL".to_string(),
+            0x00FD => "EXIT".to_string(),
+            0x00FE => "LOW".to_string(),
+            0x00FF => "HIGH".to_string(),
             _ => format!("SYS  {:03X}", nnn),
         },
         0x1000 => format!("JP   {:03X}", nnn),
         0x2000 => format!("CALL {:03X}", nnn),
         0x3000 => format!("SE   V{:X}, {:02X}", x, nn),
         0x4000 => format!("SNE  V{:X}, {:02X}", x, nn),
-        0x5000 => format!("SE   V{:X}, V{:X}", x, y),
+        0x5000 => match n {
+            0x0 => format!("SE   V{:X}, V{:X}", x, y),
+            0x2 => format!("LD   [I], V{:X}-V{:X}", x, y),
+            0x3 => format!("LD   V{:X}-V{:X}, [I]", x, y),
+            _ => format!("DATA {:04X}", opcode),
+        },
         0x6000 => format!("LD   V{:X}, {:02X}", x, nn),
         0x7000 => format!("ADD  V{:X}, {:02X}", x, nn),
         0x8000 => match n {
@@ -25,12 +37,14 @@ pub fn disassemble(opcode: u16) -> String {
             0x3 => format!("XOR  V{:X}, V{:X}", x, y),
             0x4 => format!("ADD  V{:X}, V{:X}", x, y),
             0x5 => format!("SUB  V{:X}, V{:X}", x, y),
-            0x6 => format!("SHR  V{:X}", x),
+            0x6 if y == 0 => format!("SHR  V{:X}", x),
+            0x6 => format!("SHR  V{:X}, V{:X}", x, y),
             0x7 => format!("SUBN V{:X}, V{:X}", x, y),
-            0xE => format!("SHL  V{:X}", x),
+            0xE if y == 0 => format!("SHL  V{:X}", x),
+            0xE => format!("SHL  V{:X}, V{:X}", x, y),
             _ => format!("DATA {:04X}", opcode),
         },
-        0x9000 => format!("SNE  V{:X}, V{:X}", x, y),
+        0x9000 if n == 0 => format!("SNE  V{:X}, V{:X}", x, y),
         0xA000 => format!("LD   I, {:03X}", nnn),
         0xB000 => format!("JP   V0, {:03X}", nnn),
         0xC000 => format!("RND  V{:X}, {:02X}", x, nn),
@@ -41,19 +55,82 @@ pub fn disassemble(opcode: u16) -> String {
             _ => format!("DATA {:04X}", opcode),
         },
         0xF000 => match nn {
+            0x00 if x == 0 => "LD   I, LONG".to_string(),
+            0x01 => format!("PLANE {:X}", x),
+            0x02 if x == 0 => "AUDIO".to_string(),
             0x07 => format!("LD   V{:X}, DT", x),
             0x0A => format!("LD   V{:X}, K", x),
             0x15 => format!("LD   DT, V{:X}", x),
             0x18 => format!("LD   ST, V{:X}", x),
             0x1E => format!("ADD  I, V{:X}", x),
             0x29 => format!("LD   F, V{:X}", x),
+            0x30 => format!("LD   HF, V{:X}", x),
             0x33 => format!("LD   B, V{:X}", x),
+            0x3A => format!("PITCH V{:X}", x),
             0x55 => format!("LD   [I], V{:X}", x),
             0x65 => format!("LD   V{:X}, [I]", x),
+            0x75 => format!("LD   R, V{:X}", x),
+            0x85 => format!("LD   V{:X}, R", x),
             _ => format!("DATA {:04X}", opcode),
         },
         _ => format!("DATA {:04X}", opcode),
     }
 }
 
+/// Disassembles the instruction at `addr`, returning its text and length in
+/// bytes. Unlike [`disassemble`] this decodes the operand of the four-byte
+/// XO-CHIP `F000 NNNN` instruction.
+pub fn disassemble_at(memory: &[u8], addr: usize) -> (String, usize) {
+    let word = |at: usize| -> Option<u16> {
+        Some((*memory.get(at)? as u16) << 8 | *memory.get(at + 1)? as u16)
+    };
+
+    match word(addr) {
+        Some(0xF000) => match word(addr + 2) {
+            Some(nnnn) => (format!("LD   I, LONG {:04X}", nnnn), 4),
+            None => (disassemble(0xF000), 2),
+        },
+        Some(opcode) => (disassemble(opcode), 2),
+        None => ("DATA".to_string(), 2),
+    }
+}
+
+/// The address of the instruction that ends at `addr`, assuming it was two
+/// bytes long unless the word before it is the `F000` prefix of a long load.
+pub fn instruction_before(memory: &[u8], addr: usize) -> Option<usize> {
+    let word = |at: usize| Some((*memory.get(at)? as u16) << 8 | *memory.get(at + 1)? as u16);
+    match addr.checked_sub(4) {
+        Some(long) if word(long) == Some(0xF000) => Some(long),
+        _ => addr.checked_sub(2),
+    }
+}
 
+/// The address operand of `JP`, `CALL`, `LD I` and `JP V0`.
+pub fn address_operand(opcode: u16) -> Option<u16> {
+    match opcode & 0xF000 {
+        0x1000 | 0x2000 | 0xA000 | 0xB000 => Some(opcode & 0x0FFF),
+        _ => None,
+    }
+}
+
+/// Like [`disassemble_at`], but writes the address operand of jumps, calls
+/// and `LD I` (always the last operand) as a label when `label` has one.
+pub fn disassemble_at_labeled(
+    memory: &[u8],
+    addr: usize,
+    label: &dyn Fn(u16) -> Option<String>,
+) -> (String, usize) {
+    let (text, len) = disassemble_at(memory, addr);
+    let word = |at: usize| Some((*memory.get(at)? as u16) << 8 | *memory.get(at + 1)? as u16);
+
+    let target = match word(addr) {
+        Some(0xF000) if len == 4 => word(addr + 2),
+        Some(opcode) => address_operand(opcode),
+        None => None,
+    };
+
+    match (target.and_then(label), text.rsplit_once(' ')) {
+        (Some(name), Some((instruction, _))) => (format!("{} {}", instruction, name), len),
+        _ => (text, len),
+    }
+}
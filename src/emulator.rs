@@ -1,3 +1,12 @@
+use crate::breakpoint::Breakpoint;
+use crate::fault::Fault;
+use crate::history::History;
+use crate::quirks::{Platform, Quirks};
+use crate::rng::{self, Rng};
+use crate::watchpoint::{Access, WatchHit, Watchpoint};
+use sha1::{Digest, Sha1};
+use std::collections::BTreeMap;
+
 const FONT_SET: [u8; 80] = [
     0xF0, 0x90, 0x90, 0x90, 0xF0, 
     0x20, 0x60, 0x20, 0x20, 0x70, 
@@ -17,10 +26,85 @@ const FONT_SET: [u8; 80] = [
     0xF0, 0x80, 0xF0, 0x80, 0x80, 
 ];
 
+/// What a single call to [`Emulator::run_frame`] produced.
+#[derive(Clone, Debug)]
+pub struct FrameOutput {
+    pub width: usize,
+    pub height: usize,
+
+    /// Row-major `width * height` pixels, each a bitmask of the planes lit.
+    pub display: Vec<u8>,
+
+    /// Whether the buzzer should be sounding during this frame.
+    pub sound: bool,
+
+    pub cycles: u32,
+
+    /// Set when the frame ended early because a breakpoint or watchpoint
+    /// was reached.
+    pub stop: Option<StopReason>,
+}
+
+/// Why execution stopped before the end of a frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    Breakpoint(u16),
+    Watchpoint(WatchHit),
+    Finished(RunUntil),
+}
+
+/// A one-off place to stop, used by the debugger's stepping commands.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunUntil {
+    /// PC reaches the address, at any stack depth (run to cursor).
+    Address(u16),
+
+    /// PC reaches `addr` with the stack back at `sp` (step over a call).
+    Return { addr: u16, sp: u8 },
+
+    /// The stack drops below `sp` (step out of a subroutine).
+    StackBelow(u8),
+}
+
+/// SUPER-CHIP 8x10 digits, stored right after the small font. Octo's A-F
+/// glyphs are included so `FX30` works for every nibble.
+const BIG_FONT_SET: [u8; 160] = [
+    0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF,
+    0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF,
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF,
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF,
+    0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03,
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF,
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF,
+    0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18,
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF,
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF,
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3,
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC,
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C,
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC,
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF,
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0,
+];
+
+pub const BIG_FONT_ADDR: u16 = 0x50;
+
+pub const LORES_WIDTH: usize = 64;
+pub const LORES_HEIGHT: usize = 32;
+pub const HIRES_WIDTH: usize = 128;
+pub const HIRES_HEIGHT: usize = 64;
+
+pub const MEMORY_SIZE: usize = 4096;
+pub const XO_MEMORY_SIZE: usize = 65536;
+
 #[derive(Clone)]
 pub struct Emulator {
    
-    pub memory: [u8; 4096],
+    /// 4 KiB normally, 64 KiB on XO-CHIP.
+    pub memory: Vec<u8>,
+
+    /// The program as loaded, used by reset and to identify save states.
+    pub rom: Vec<u8>,
 
 
     pub v: [u8; 16],
@@ -38,7 +122,25 @@ pub struct Emulator {
     
     pub keypad: [bool; 16],
     
-    pub display: [bool; 64 * 32],
+    /// Row-major framebuffer; only the first `width() * height()` entries
+    /// are in use, laid out with a stride of `width()`. Each pixel is a
+    /// bitmask with one bit per XO-CHIP bitplane.
+    pub display: [u8; HIRES_WIDTH * HIRES_HEIGHT],
+
+    /// Bitplanes affected by drawing, clearing and scrolling (`FN01`).
+    pub planes: u8,
+
+    pub hires: bool,
+
+    /// Set by the SUPER-CHIP `00FD` instruction.
+    pub exited: bool,
+
+    /// SUPER-CHIP "RPL" user flags, saved and restored by `FX75`/`FX85`.
+    pub rpl: [u8; 16],
+
+    /// XO-CHIP audio pattern buffer (`F002`) and playback pitch (`FX3A`).
+    pub audio_pattern: [u8; 16],
+    pub pitch: u8,
     
     pub paused: bool,
     
@@ -46,13 +148,56 @@ pub struct Emulator {
     
     pub checkpoint: Option<Box<Emulator>>,
     
-    pub ghost_display: [u8; 64 * 32],
+    pub ghost_display: [u8; HIRES_WIDTH * HIRES_HEIGHT],
+
+    pub cycles_per_frame: u32,
+
+    /// Instructions executed and frames completed since the last reset.
+    pub instruction_count: u64,
+    pub frame_count: u64,
+
+    pub quirks: Quirks,
+
+    /// Generator behind `CXNN`, and the seed it started from so a reset
+    /// replays the same sequence.
+    pub rng: Rng,
+    pub seed: u64,
+
+    /// Set by `DXYN` under the display wait quirk; ends the current frame early.
+    pub vblank_wait: bool,
+
+    /// Breakpoints by address; execution stops when PC reaches one whose
+    /// condition holds.
+    pub breakpoints: BTreeMap<u16, Breakpoint>,
+
+    pub watchpoints: Vec<Watchpoint>,
+
+    /// First watched access made by the instruction being executed.
+    pub watch_hit: Option<WatchHit>,
+
+    /// Cleared once reached, or when execution stops for another reason.
+    pub run_until: Option<RunUntil>,
+
+    /// Recently executed instructions, for stepping backwards. Recording is
+    /// off until this is replaced with [`History::new`].
+    pub history: History,
+
+    /// Set by `cycle` when execution should stop, before the instruction
+    /// under a breakpoint or execute watchpoint, or after one that made a
+    /// watched access or finished a [`RunUntil`]; `run_frame` hands it back through
+    /// [`FrameOutput::stop`].
+    pub stop_reason: Option<StopReason>,
+
+    /// A breakpoint here is passed over once, so that resuming from it runs
+    /// the instruction under it instead of stopping again straight away.
+    pub resume_pc: Option<u16>,
 }
 
 impl Emulator {
     pub fn new() -> Self {
         let mut emulator = Self {
-            memory: [0; 4096],
+            memory: vec![0; MEMORY_SIZE],
+            rom: Vec::new(),
             v: [0; 16],
             i: 0,
             pc: 0x200, 
@@ -61,37 +206,343 @@ impl Emulator {
             delay_timer: 0,
             sound_timer: 0,
             keypad: [false; 16],
-            display: [false; 64 * 32],
+            display: [0; HIRES_WIDTH * HIRES_HEIGHT],
+            planes: 1,
+            hires: false,
+            exited: false,
+            rpl: [0; 16],
+            audio_pattern: [0; 16],
+            pitch: 64,
             paused: false,
             waiting_for_key: None,
             checkpoint: None,
-            ghost_display: [0; 64 * 32],
+            ghost_display: [0; HIRES_WIDTH * HIRES_HEIGHT],
+            cycles_per_frame: 10,
+            instruction_count: 0,
+            frame_count: 0,
+            quirks: Quirks::default(),
+            rng: Rng::new(0),
+            seed: 0,
+            vblank_wait: false,
+            breakpoints: BTreeMap::new(),
+            watchpoints: Vec::new(),
+            watch_hit: None,
+            run_until: None,
+            history: History::default(),
+            stop_reason: None,
+            resume_pc: None,
         };
 
+        emulator.set_seed(rng::random_seed());
+
         emulator.memory[0..80].copy_from_slice(&FONT_SET);
+        let big_font = BIG_FONT_ADDR as usize;
+        emulator.memory[big_font..big_font + BIG_FONT_SET.len()].copy_from_slice(&BIG_FONT_SET);
 
         emulator
     }
 
+    /// Creates an emulator with the quirks of `platform`, and 64 KiB of
+    /// memory for XO-CHIP.
+    pub fn with_platform(platform: Platform) -> Self {
+        let mut emulator = Self::new();
+        emulator.quirks = platform.quirks();
+        if platform == Platform::XoChip {
+            emulator.memory.resize(XO_MEMORY_SIZE, 0);
+        }
+        emulator
+    }
+
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = Rng::new(seed);
+    }
+
     pub fn load_rom(&mut self, data: &[u8]) {
         let start = 0x200;
-        let end = start + data.len().min(0xE00); 
+        let end = start + data.len().min(self.memory.len() - start); 
         self.memory[start..end].copy_from_slice(&data[..end - start]);
+        self.rom = data[..end - start].to_vec();
+    }
+
+    /// Hex SHA-1 of the loaded ROM.
+    pub fn rom_sha1(&self) -> String {
+        rom_sha1(&self.rom)
+    }
+
+    pub fn fetch(&self) -> Result<u16, Fault> {
+        self.peek_word(self.pc as usize)
+    }
+
+    /// Reads a big-endian word without triggering watchpoints, for
+    /// instruction fetches.
+    pub fn peek_word(&self, addr: usize) -> Result<u16, Fault> {
+        let byte = |addr: usize| {
+            self.memory
+                .get(addr)
+                .copied()
+                .ok_or(Fault::MemoryOutOfBounds { addr })
+        };
+        Ok((byte(addr)? as u16) << 8 | byte(addr + 1)? as u16)
+    }
+
+    /// Reads a byte on behalf of an instruction, checking read watchpoints.
+    pub fn read_byte(&mut self, addr: usize) -> Result<u8, Fault> {
+        let value = self
+            .memory
+            .get(addr)
+            .copied()
+            .ok_or(Fault::MemoryOutOfBounds { addr })?;
+        self.watch(addr, Access::Read, value, value);
+        Ok(value)
+    }
+
+    /// Writes a byte on behalf of an instruction, checking write watchpoints.
+    pub fn write_byte(&mut self, addr: usize, value: u8) -> Result<(), Fault> {
+        match self.memory.get_mut(addr) {
+            Some(byte) => {
+                let old = std::mem::replace(byte, value);
+                self.history.note_write(addr, old);
+                self.watch(addr, Access::Write, old, value);
+                Ok(())
+            }
+            None => Err(Fault::MemoryOutOfBounds { addr }),
+        }
+    }
+
+    fn watch(&mut self, addr: usize, access: Access, old: u8, new: u8) {
+        if self.watch_hit.is_none() && self.watchpoints.iter().any(|w| w.matches(addr, access)) {
+            self.watch_hit = Some(WatchHit {
+                pc: self.pc,
+                opcode: 0,
+                addr,
+                access,
+                old,
+                new,
+            });
+        }
+    }
+
+    /// Checks that `len` bytes starting at `addr` are all inside memory, so a
+    /// multi-byte access either happens completely or not at all.
+    pub fn check_range(&self, addr: usize, len: usize) -> Result<(), Fault> {
+        if len > 0 && addr + len > self.memory.len() {
+            return Err(Fault::MemoryOutOfBounds { addr: self.memory.len().max(addr) });
+        }
+        Ok(())
+    }
+
+    pub fn width(&self) -> usize {
+        if self.hires { HIRES_WIDTH } else { LORES_WIDTH }
+    }
+
+    pub fn height(&self) -> usize {
+        if self.hires { HIRES_HEIGHT } else { LORES_HEIGHT }
+    }
+
+    /// Switches between 64x32 and 128x64 mode, clearing the screen.
+    pub fn set_hires(&mut self, hires: bool) {
+        self.hires = hires;
+        self.display = [0; HIRES_WIDTH * HIRES_HEIGHT];
+        self.ghost_display = [0; HIRES_WIDTH * HIRES_HEIGHT];
+    }
+
+    pub fn cycle(&mut self) -> Result<(), Fault> {
+        self.cycle_with(&mut |_| {})
+    }
+
+    /// Like [`Emulator::cycle`], calling `observe` with the emulator just
+    /// before the instruction executes, e.g. to trace it.
+    pub fn cycle_with(&mut self, observe: &mut dyn FnMut(&Emulator)) -> Result<(), Fault> {
+        if self.waiting_for_key.is_some() || self.exited {
+            return Ok(());
+        }
+
+        // Breakpoints and execute watchpoints stop before the instruction
+        // under them runs, so one on the very first instruction is honoured
+        // too.
+        let resuming = self.resume_pc.take() == Some(self.pc);
+        if !resuming {
+            let reason = if self.breakpoint_reached() {
+                Some(StopReason::Breakpoint(self.pc))
+            } else {
+                self.execute_watch_hit().map(StopReason::Watchpoint)
+            };
+            if reason.is_some() {
+                self.stop_reason = reason;
+                self.resume_pc = Some(self.pc);
+                self.run_until = None;
+                return Ok(());
+            }
+        }
+
+        let pc = self.pc;
+        let opcode = self.fetch()?;
+        observe(self);
+
+        self.watch_hit = None;
+        History::begin(self, opcode);
+        if let Err(fault) = crate::opcodes::execute(self, opcode) {
+            self.history.abort();
+            return Err(fault);
+        }
+        History::commit(self);
+        self.instruction_count += 1;
+
+        self.stop_reason = self.check_stop(pc, opcode);
+        if self.stop_reason.is_some() {
+            self.run_until = None;
+        }
+
+        Ok(())
+    }
+
+    /// Decides whether execution should stop after the instruction at `pc`.
+    fn check_stop(&mut self, pc: u16, opcode: u16) -> Option<StopReason> {
+        // The instruction is allowed to finish, so a watchpoint stops with PC
+        // on the instruction after the one that made the access.
+        if let Some(mut hit) = self.watch_hit.take() {
+            hit.pc = pc;
+            hit.opcode = opcode;
+            return Some(StopReason::Watchpoint(hit));
+        }
+
+        let target = self.run_until?;
+        let reached = match target {
+            RunUntil::Address(addr) => self.pc == addr,
+            RunUntil::Return { addr, sp } => self.pc == addr && self.sp <= sp,
+            RunUntil::StackBelow(sp) => self.sp < sp,
+        };
+        reached.then_some(StopReason::Finished(target))
+    }
+
+    /// Counts a hit if PC is on a breakpoint whose condition holds, and
+    /// decides whether that hit stops execution. A breakpoint with a hit
+    /// target stops on every Nth hit, counting again from zero each time.
+    fn breakpoint_reached(&mut self) -> bool {
+        let Some(breakpoint) = self.breakpoints.get(&self.pc) else {
+            return false;
+        };
+        let condition_holds = match &breakpoint.condition {
+            Some(condition) => condition.expr.is_true(self),
+            None => true,
+        };
+        if !condition_holds {
+            return false;
+        }
+
+        let breakpoint = self.breakpoints.get_mut(&self.pc).unwrap();
+        breakpoint.hits += 1;
+        match breakpoint.hit_target {
+            Some(target) if breakpoint.hits >= target => {
+                breakpoint.hits = 0;
+                true
+            }
+            Some(_) => false,
+            None => true,
+        }
+    }
+
+    /// An execute watchpoint hit for the instruction at PC, if one is set.
+    fn execute_watch_hit(&self) -> Option<WatchHit> {
+        let addr = self.pc as usize;
+        if !self.watchpoints.iter().any(|w| w.matches(addr, Access::Execute)) {
+            return None;
+        }
+        Some(WatchHit {
+            pc: self.pc,
+            opcode: self.fetch().ok()?,
+            addr,
+            access: Access::Execute,
+            old: 0,
+            new: 0,
+        })
     }
 
-    pub fn fetch(&self) -> u16 {
-        let pc = self.pc as usize;
-        (self.memory[pc] as u16) << 8 | self.memory[pc + 1] as u16
+    /// Lets the next instruction run even if a breakpoint is set on it, as
+    /// when single-stepping or continuing from where execution stopped.
+    pub fn resume(&mut self) {
+        self.resume_pc = Some(self.pc);
     }
 
-    pub fn cycle(&mut self) {
-        if self.waiting_for_key.is_some() {
-            return;
+    /// Undoes the last instruction recorded in [`Emulator::history`].
+    /// Returns false once the history is exhausted.
+    pub fn step_back(&mut self) -> bool {
+        History::step_back(self)
+    }
+
+    /// Steps backwards until PC is on a breakpoint whose condition holds,
+    /// returning its address, or until the history runs out.
+    pub fn reverse_continue(&mut self) -> Option<u16> {
+        while self.step_back() {
+            if let Some(breakpoint) = self.breakpoints.get(&self.pc) {
+                let condition_holds = match &breakpoint.condition {
+                    Some(condition) => condition.expr.is_true(self),
+                    None => true,
+                };
+                if condition_holds {
+                    self.resume();
+                    return Some(self.pc);
+                }
+            }
         }
+        None
+    }
 
-        let opcode = self.fetch();
+    /// Sets a breakpoint at `addr`, or clears it if one is already set.
+    /// Returns whether a breakpoint is now set.
+    pub fn toggle_breakpoint(&mut self, addr: u16) -> bool {
+        if self.breakpoints.remove(&addr).is_some() {
+            false
+        } else {
+            self.breakpoints.insert(addr, Breakpoint::default());
+            true
+        }
+    }
 
-        crate::opcodes::execute(self, opcode);
+    /// Runs one 60 Hz frame: `cycles_per_frame` instructions followed by a
+    /// timer tick. This is the headless entry point; the TUI calls it too.
+    ///
+    /// A fault stops the frame immediately, before the timers are updated.
+    /// Reaching a breakpoint or watchpoint also ends the frame early,
+    /// reported through [`FrameOutput::stop`].
+    pub fn run_frame(&mut self, keypad: [bool; 16]) -> Result<FrameOutput, Fault> {
+        self.run_frame_with(keypad, &mut |_| {})
+    }
+
+    /// Like [`Emulator::run_frame`], calling `observe` before each
+    /// instruction as [`Emulator::cycle_with`] does.
+    pub fn run_frame_with(
+        &mut self,
+        keypad: [bool; 16],
+        observe: &mut dyn FnMut(&Emulator),
+    ) -> Result<FrameOutput, Fault> {
+        self.keypad = keypad;
+        self.vblank_wait = false;
+        self.stop_reason = None;
+
+        let mut cycles = 0;
+        for _ in 0..self.cycles_per_frame {
+            self.cycle_with(observe)?;
+            cycles += 1;
+            if self.vblank_wait || self.stop_reason.is_some() {
+                break;
+            }
+        }
+
+        self.update_timers();
+        self.update_ghost();
+        self.frame_count += 1;
+
+        let (width, height) = (self.width(), self.height());
+        Ok(FrameOutput {
+            width,
+            height,
+            display: self.display[..width * height].to_vec(),
+            sound: self.sound_timer > 0,
+            cycles,
+            stop: self.stop_reason.take(),
+        })
     }
 
     pub fn update_timers(&mut self) {
@@ -104,8 +555,8 @@ impl Emulator {
     }
 
     pub fn update_ghost(&mut self) {
-        for i in 0..self.ghost_display.len() {
-            if self.display[i] {
+        for i in 0..self.width() * self.height() {
+            if self.display[i] != 0 {
                 self.ghost_display[i] = 255; // Full brightness
             } else if self.ghost_display[i] > 0 {
                 self.ghost_display[i] = self.ghost_display[i].saturating_sub(25); // Decay
@@ -114,7 +565,9 @@ impl Emulator {
     }
 
     pub fn save_checkpoint(&mut self) {
+        let history = std::mem::take(&mut self.history);
         let mut clone = self.clone();
+        self.history = history;
         clone.checkpoint = None;
         self.checkpoint = Some(Box::new(clone));
     }
@@ -122,16 +575,162 @@ impl Emulator {
     pub fn load_checkpoint(&mut self) {
         if let Some(checkpoint) = &self.checkpoint {
             let saved_checkpoint = self.checkpoint.clone();
+            let breakpoints = std::mem::take(&mut self.breakpoints);
+            let watchpoints = std::mem::take(&mut self.watchpoints);
+            let mut history = std::mem::take(&mut self.history);
             *self = (**checkpoint).clone();
             self.checkpoint = saved_checkpoint;
+            self.breakpoints = breakpoints;
+            self.watchpoints = watchpoints;
+            history.clear();
+            self.history = history;
         }
     }
 
     pub fn reset(&mut self) {
-        let rom_data: Vec<u8> = self.memory[0x200..].to_vec();
+        let rom_data = std::mem::take(&mut self.rom);
+        let memory_size = self.memory.len();
+        let cycles_per_frame = self.cycles_per_frame;
+        let quirks = self.quirks;
+        let seed = self.seed;
+        let breakpoints = std::mem::take(&mut self.breakpoints);
+        let watchpoints = std::mem::take(&mut self.watchpoints);
+        let mut history = std::mem::take(&mut self.history);
         *self = Self::new();
-        self.memory[0x200..].copy_from_slice(&rom_data);
+        self.breakpoints = breakpoints;
+        self.watchpoints = watchpoints;
+        history.clear();
+        self.history = history;
+        self.set_seed(seed);
+        self.memory.resize(memory_size, 0);
+        self.load_rom(&rom_data);
+        self.cycles_per_frame = cycles_per_frame;
+        self.quirks = quirks;
     }
 }
 
+impl Default for Emulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Hex SHA-1 of a ROM image, which identifies it in save states and the ROM
+/// database.
+pub fn rom_sha1(rom: &[u8]) -> String {
+    Sha1::digest(rom)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::breakpoint::Condition;
+
+    /// `ADD V0, 1` at 0x200 in a loop with a `JP 0x200` at 0x202.
+    fn counter() -> Emulator {
+        let mut emu = Emulator::new();
+        emu.load_rom(&[0x70, 0x01, 0x12, 0x00]);
+        emu
+    }
+
+    fn stop(emu: &mut Emulator) -> Option<StopReason> {
+        emu.run_frame([false; 16]).unwrap().stop
+    }
+
+    #[test]
+    fn breakpoints_on_the_first_instruction_stop_before_it_runs() {
+        let mut emu = counter();
+        emu.toggle_breakpoint(0x200);
+        assert_eq!(stop(&mut emu), Some(StopReason::Breakpoint(0x200)));
+        assert_eq!((emu.pc, emu.v[0], emu.instruction_count), (0x200, 0, 0));
+    }
+
+    #[test]
+    fn resuming_runs_the_instruction_under_the_breakpoint() {
+        let mut emu = counter();
+        emu.toggle_breakpoint(0x200);
+        for count in 0..3 {
+            assert_eq!(stop(&mut emu), Some(StopReason::Breakpoint(0x200)));
+            assert_eq!(emu.v[0], count);
+        }
+    }
 
+    #[test]
+    fn stepping_from_a_breakpoint_runs_it() {
+        let mut emu = counter();
+        emu.toggle_breakpoint(0x200);
+        emu.resume();
+        emu.cycle().unwrap();
+        assert_eq!((emu.pc, emu.v[0], emu.stop_reason), (0x202, 1, None));
+    }
+
+    #[test]
+    fn breakpoints_stop_only_when_their_condition_holds() {
+        let mut emu = counter();
+        let breakpoint = Breakpoint {
+            condition: Some(Condition::parse("V0 == 3").unwrap()),
+            ..Breakpoint::default()
+        };
+        emu.breakpoints.insert(0x200, breakpoint);
+        assert_eq!(stop(&mut emu), Some(StopReason::Breakpoint(0x200)));
+        assert_eq!(emu.v[0], 3);
+        assert_eq!(emu.breakpoints[&0x200].hits, 1);
+    }
+
+    #[test]
+    fn hit_counts_stop_on_every_nth_hit() {
+        let mut emu = counter();
+        let breakpoint = Breakpoint {
+            hit_target: Some(3),
+            ..Breakpoint::default()
+        };
+        emu.breakpoints.insert(0x200, breakpoint);
+        assert_eq!(stop(&mut emu), Some(StopReason::Breakpoint(0x200)));
+        assert_eq!((emu.v[0], emu.breakpoints[&0x200].hits), (2, 0));
+        assert_eq!(stop(&mut emu), Some(StopReason::Breakpoint(0x200)));
+        assert_eq!((emu.v[0], emu.breakpoints[&0x200].hits), (5, 0));
+    }
+
+    #[test]
+    fn reverse_continue_lands_on_a_breakpoint_and_can_resume() {
+        let mut emu = counter();
+        emu.history = History::new(100);
+        for _ in 0..9 {
+            emu.cycle().unwrap();
+        }
+        emu.toggle_breakpoint(0x200);
+        assert_eq!(emu.reverse_continue(), Some(0x200));
+        assert_eq!(emu.v[0], 4);
+        emu.cycle().unwrap();
+        assert_eq!((emu.pc, emu.v[0]), (0x202, 5));
+    }
+
+    #[test]
+    fn seeded_runs_roll_the_same_numbers() {
+        // RND V0, FF in a loop, collecting V0 after each roll.
+        let rolls = |seed: u64| {
+            let mut emu = Emulator::new();
+            emu.set_seed(seed);
+            emu.load_rom(&[0xC0, 0xFF, 0x12, 0x00]);
+            let mut rolls = Vec::new();
+            for _ in 0..16 {
+                emu.cycle().unwrap();
+                emu.cycle().unwrap();
+                rolls.push(emu.v[0]);
+            }
+            (rolls, emu)
+        };
+
+        let (first, mut emu) = rolls(5);
+        assert_eq!(rolls(5).0, first);
+        assert_ne!(rolls(6).0, first);
+
+        // A reset starts over from the same seed.
+        emu.reset();
+        emu.cycle().unwrap();
+        assert_eq!(emu.v[0], first[0]);
+    }
+}
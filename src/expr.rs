@@ -0,0 +1,520 @@
+//! A small expression language over emulator state, used for breakpoint
+//! conditions and addresses typed into the inspector.
+//!
+//! ```text
+//! V3 == 0x10 && I >= 0x300
+//! [I+2] != 0
+//! DT == 0 || SP > 4
+//! ```
+//!
+//! Operands are numbers (decimal, or hex with `0x`), the registers `V0`-`VF`,
+//! `I`, `PC`, `SP`, `DT` and `ST`, and memory bytes written `[address]`.
+//! Operators follow C precedence. Comparisons and logic operators produce 1
+//! or 0, and any non-zero value counts as true. Reading outside memory or
+//! dividing by zero yields 0 rather than an error, so a condition can always
+//! be evaluated.
+
+use crate::emulator::Emulator;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Expr {
+    Number(i64),
+    Register(Register),
+    Memory(Box<Expr>),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Register {
+    V(u8),
+    I,
+    Pc,
+    Sp,
+    Dt,
+    St,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+    BitNot,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryOp {
+    Or,
+    And,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Shl,
+    Shr,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl BinaryOp {
+    fn precedence(self) -> u8 {
+        match self {
+            BinaryOp::Or => 1,
+            BinaryOp::And => 2,
+            BinaryOp::BitOr => 3,
+            BinaryOp::BitXor => 4,
+            BinaryOp::BitAnd => 5,
+            BinaryOp::Eq | BinaryOp::Ne => 6,
+            BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => 7,
+            BinaryOp::Shl | BinaryOp::Shr => 8,
+            BinaryOp::Add | BinaryOp::Sub => 9,
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => 10,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+
+    /// Character offset into the source where the error was found.
+    pub position: usize,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at column {}", self.message, self.position + 1)
+    }
+}
+
+impl Error for ParseError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Number(i64),
+    Ident(String),
+    Op(&'static str),
+    LBracket,
+    RBracket,
+    LParen,
+    RParen,
+}
+
+/// Operators, longest first so that `<=` is not read as `<` then `=`.
+const OPERATORS: [&str; 21] = [
+    "&&", "||", "==", "!=", "<=", ">=", "<<", ">>", "<", ">", "+", "-", "*", "/", "%", "&", "|",
+    "^", "!", "~", "=",
+];
+
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, ParseError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    while pos < chars.len() {
+        let c = chars[pos];
+
+        if c.is_whitespace() {
+            pos += 1;
+            continue;
+        }
+
+        let start = pos;
+
+        if c.is_ascii_digit() {
+            while pos < chars.len() && chars[pos].is_ascii_alphanumeric() {
+                pos += 1;
+            }
+            let text: String = chars[start..pos].iter().collect();
+            let value = parse_number(&text).ok_or_else(|| ParseError {
+                message: format!("invalid number '{}'", text),
+                position: start,
+            })?;
+            tokens.push((Token::Number(value), start));
+            continue;
+        }
+
+        if c.is_ascii_alphabetic() || c == '_' {
+            while pos < chars.len() && (chars[pos].is_ascii_alphanumeric() || chars[pos] == '_') {
+                pos += 1;
+            }
+            tokens.push((Token::Ident(chars[start..pos].iter().collect()), start));
+            continue;
+        }
+
+        let bracket = match c {
+            '[' => Some(Token::LBracket),
+            ']' => Some(Token::RBracket),
+            '(' => Some(Token::LParen),
+            ')' => Some(Token::RParen),
+            _ => None,
+        };
+        if let Some(token) = bracket {
+            tokens.push((token, start));
+            pos += 1;
+            continue;
+        }
+
+        let rest: String = chars[pos..].iter().take(2).collect();
+        match OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            Some(&"=") => {
+                return Err(ParseError {
+                    message: "'=' is not an operator, use '=='".to_string(),
+                    position: start,
+                })
+            }
+            Some(op) => {
+                tokens.push((Token::Op(op), start));
+                pos += op.len();
+            }
+            None => {
+                return Err(ParseError {
+                    message: format!("unexpected character '{}'", c),
+                    position: start,
+                })
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Parses a decimal, `0x` hexadecimal or `0b` binary literal.
+pub fn parse_number(text: &str) -> Option<i64> {
+    let lower = text.to_ascii_lowercase();
+    if let Some(hex) = lower.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()
+    } else if let Some(binary) = lower.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2).ok()
+    } else {
+        lower.parse().ok()
+    }
+}
+
+fn register(name: &str) -> Option<Register> {
+    let upper = name.to_ascii_uppercase();
+    match upper.as_str() {
+        "I" => Some(Register::I),
+        "PC" => Some(Register::Pc),
+        "SP" => Some(Register::Sp),
+        "DT" => Some(Register::Dt),
+        "ST" => Some(Register::St),
+        _ => {
+            let index = upper.strip_prefix('V')?;
+            if index.len() != 1 {
+                return None;
+            }
+            u8::from_str_radix(index, 16).ok().map(Register::V)
+        }
+    }
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens.get(self.pos).map_or(self.end, |(_, at)| *at)
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, ParseError> {
+        Err(ParseError {
+            message: message.into(),
+            position: self.position(),
+        })
+    }
+
+    fn expect(&mut self, expected: Token, what: &str) -> Result<(), ParseError> {
+        if self.peek() == Some(&expected) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            self.error(format!("expected '{}'", what))
+        }
+    }
+
+    fn binary_op(&self) -> Option<BinaryOp> {
+        let op = match self.peek()? {
+            Token::Op(op) => *op,
+            _ => return None,
+        };
+        Some(match op {
+            "||" => BinaryOp::Or,
+            "&&" => BinaryOp::And,
+            "|" => BinaryOp::BitOr,
+            "^" => BinaryOp::BitXor,
+            "&" => BinaryOp::BitAnd,
+            "==" => BinaryOp::Eq,
+            "!=" => BinaryOp::Ne,
+            "<" => BinaryOp::Lt,
+            "<=" => BinaryOp::Le,
+            ">" => BinaryOp::Gt,
+            ">=" => BinaryOp::Ge,
+            "<<" => BinaryOp::Shl,
+            ">>" => BinaryOp::Shr,
+            "+" => BinaryOp::Add,
+            "-" => BinaryOp::Sub,
+            "*" => BinaryOp::Mul,
+            "/" => BinaryOp::Div,
+            "%" => BinaryOp::Rem,
+            _ => return None,
+        })
+    }
+
+    /// Precedence climbing: parses operators binding at least as tightly as
+    /// `min_precedence`.
+    fn expression(&mut self, min_precedence: u8) -> Result<Expr, ParseError> {
+        let mut lhs = self.unary()?;
+
+        while let Some(op) = self.binary_op() {
+            if op.precedence() < min_precedence {
+                break;
+            }
+            self.pos += 1;
+            let rhs = self.expression(op.precedence() + 1)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        let op = match self.peek() {
+            Some(Token::Op("-")) => Some(UnaryOp::Neg),
+            Some(Token::Op("!")) => Some(UnaryOp::Not),
+            Some(Token::Op("~")) => Some(UnaryOp::BitNot),
+            _ => None,
+        };
+
+        match op {
+            Some(op) => {
+                self.pos += 1;
+                Ok(Expr::Unary(op, Box::new(self.unary()?)))
+            }
+            None => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        let token = match self.peek() {
+            Some(token) => token.clone(),
+            None => return self.error("expected a value"),
+        };
+
+        match token {
+            Token::Number(value) => {
+                self.pos += 1;
+                Ok(Expr::Number(value))
+            }
+            Token::Ident(name) => match register(&name) {
+                Some(register) => {
+                    self.pos += 1;
+                    Ok(Expr::Register(register))
+                }
+                None => self.error(format!("unknown register '{}'", name)),
+            },
+            Token::LBracket => {
+                self.pos += 1;
+                let address = self.expression(0)?;
+                self.expect(Token::RBracket, "]")?;
+                Ok(Expr::Memory(Box::new(address)))
+            }
+            Token::LParen => {
+                self.pos += 1;
+                let inner = self.expression(0)?;
+                self.expect(Token::RParen, ")")?;
+                Ok(inner)
+            }
+            _ => self.error("expected a value"),
+        }
+    }
+}
+
+impl Expr {
+    pub fn parse(source: &str) -> Result<Expr, ParseError> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            pos: 0,
+            end: source.chars().count(),
+        };
+
+        let expr = parser.expression(0)?;
+        if parser.peek().is_some() {
+            return parser.error("unexpected input after expression");
+        }
+
+        Ok(expr)
+    }
+
+    pub fn eval(&self, emu: &Emulator) -> i64 {
+        match self {
+            Expr::Number(value) => *value,
+            Expr::Register(register) => match register {
+                Register::V(index) => emu.v[*index as usize] as i64,
+                Register::I => emu.i as i64,
+                Register::Pc => emu.pc as i64,
+                Register::Sp => emu.sp as i64,
+                Register::Dt => emu.delay_timer as i64,
+                Register::St => emu.sound_timer as i64,
+            },
+            Expr::Memory(address) => usize::try_from(address.eval(emu))
+                .ok()
+                .and_then(|addr| emu.memory.get(addr))
+                .map_or(0, |&byte| byte as i64),
+            Expr::Unary(op, operand) => {
+                let value = operand.eval(emu);
+                match op {
+                    UnaryOp::Neg => value.wrapping_neg(),
+                    UnaryOp::Not => (value == 0) as i64,
+                    UnaryOp::BitNot => !value,
+                }
+            }
+            Expr::Binary(BinaryOp::And, lhs, rhs) => (lhs.eval(emu) != 0 && rhs.eval(emu) != 0) as i64,
+            Expr::Binary(BinaryOp::Or, lhs, rhs) => (lhs.eval(emu) != 0 || rhs.eval(emu) != 0) as i64,
+            Expr::Binary(op, lhs, rhs) => {
+                let (a, b) = (lhs.eval(emu), rhs.eval(emu));
+                match op {
+                    BinaryOp::BitOr => a | b,
+                    BinaryOp::BitXor => a ^ b,
+                    BinaryOp::BitAnd => a & b,
+                    BinaryOp::Eq => (a == b) as i64,
+                    BinaryOp::Ne => (a != b) as i64,
+                    BinaryOp::Lt => (a < b) as i64,
+                    BinaryOp::Le => (a <= b) as i64,
+                    BinaryOp::Gt => (a > b) as i64,
+                    BinaryOp::Ge => (a >= b) as i64,
+                    BinaryOp::Shl => a.wrapping_shl(b as u32),
+                    BinaryOp::Shr => a.wrapping_shr(b as u32),
+                    BinaryOp::Add => a.wrapping_add(b),
+                    BinaryOp::Sub => a.wrapping_sub(b),
+                    BinaryOp::Mul => a.wrapping_mul(b),
+                    BinaryOp::Div => a.checked_div(b).unwrap_or(0),
+                    BinaryOp::Rem => a.checked_rem(b).unwrap_or(0),
+                    BinaryOp::And | BinaryOp::Or => unreachable!(),
+                }
+            }
+        }
+    }
+
+    pub fn is_true(&self, emu: &Emulator) -> bool {
+        self.eval(emu) != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn emulator() -> Emulator {
+        let mut emu = Emulator::new();
+        emu.v[3] = 0x10;
+        emu.v[0xF] = 1;
+        emu.i = 0x300;
+        emu.pc = 0x2F4;
+        emu.sp = 2;
+        emu.delay_timer = 60;
+        emu.sound_timer = 5;
+        emu.memory[0x300] = 0xAB;
+        emu.memory[0x302] = 7;
+        emu
+    }
+
+    fn eval(source: &str) -> i64 {
+        Expr::parse(source).unwrap_or_else(|err| panic!("{}: {}", source, err)).eval(&emulator())
+    }
+
+    fn error(source: &str) -> (String, usize) {
+        let err = Expr::parse(source).unwrap_err();
+        (err.message, err.position)
+    }
+
+    #[test]
+    fn literals() {
+        assert_eq!(eval("42"), 42);
+        assert_eq!(eval("0x2A"), 42);
+        assert_eq!(eval("0X2a"), 42);
+        assert_eq!(eval("0b101010"), 42);
+        assert_eq!(eval("-1"), -1);
+    }
+
+    #[test]
+    fn registers_and_memory() {
+        assert_eq!(eval("V3"), 0x10);
+        assert_eq!(eval("vf"), 1);
+        assert_eq!(eval("I"), 0x300);
+        assert_eq!(eval("pc"), 0x2F4);
+        assert_eq!(eval("SP + DT + ST"), 67);
+        assert_eq!(eval("[I]"), 0xAB);
+        assert_eq!(eval("[I+2]"), 7);
+        assert_eq!(eval("[0xFFFFF]"), 0);
+        assert_eq!(eval("[-1]"), 0);
+    }
+
+    #[test]
+    fn precedence_follows_c() {
+        assert_eq!(eval("2 + 3 * 4"), 14);
+        assert_eq!(eval("(2 + 3) * 4"), 20);
+        assert_eq!(eval("10 - 4 - 3"), 3);
+        assert_eq!(eval("1 << 2 + 1"), 8);
+        assert_eq!(eval("1 | 2 ^ 3 & 1"), 3);
+        assert_eq!(eval("1 + 1 == 2"), 1);
+        assert_eq!(eval("1 < 2 == 1"), 1);
+        assert_eq!(eval("0 && 1 || 1"), 1);
+        assert_eq!(eval("0 || 1 && 0"), 0);
+        assert_eq!(eval("-2 * 3"), -6);
+        assert_eq!(eval("!0 + ~0"), 0);
+    }
+
+    #[test]
+    fn conditions() {
+        let emu = emulator();
+        let holds = |source: &str| Expr::parse(source).unwrap().is_true(&emu);
+        assert!(holds("V3 == 0x10 && [I+1] == 0"));
+        assert!(holds("I >= 0x300 && PC != 0"));
+        assert!(!holds("V3 > 0x10"));
+        assert!(holds("V3 >= 0x10 && V3 <= 0x10"));
+    }
+
+    #[test]
+    fn division_by_zero_is_zero() {
+        assert_eq!(eval("5 / 0"), 0);
+        assert_eq!(eval("5 % 0"), 0);
+        assert_eq!(eval("7 / 2"), 3);
+        assert_eq!(eval("7 % 2"), 1);
+    }
+
+    #[test]
+    fn malformed_input_is_reported_with_its_position() {
+        assert_eq!(error(""), ("expected a value".to_string(), 0));
+        assert_eq!(error("V3 =="), ("expected a value".to_string(), 5));
+        assert_eq!(error("V3 = 1"), ("'=' is not an operator, use '=='".to_string(), 3));
+        assert_eq!(error("VG"), ("unknown register 'VG'".to_string(), 0));
+        assert_eq!(error("0x1G"), ("invalid number '0x1G'".to_string(), 0));
+        assert_eq!(error("[I"), ("expected ']'".to_string(), 2));
+        assert_eq!(error("(1 + 2"), ("expected ')'".to_string(), 6));
+        assert_eq!(error("1 2"), ("unexpected input after expression".to_string(), 2));
+        assert_eq!(error("V3 $ 1"), ("unexpected character '$'".to_string(), 3));
+    }
+
+    #[test]
+    fn errors_display_a_column() {
+        assert_eq!(Expr::parse("V3 = 1").unwrap_err().to_string(), "'=' is not an operator, use '==' at column 4");
+    }
+}
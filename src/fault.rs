@@ -0,0 +1,33 @@
+use std::error::Error;
+use std::fmt;
+
+/// An instruction the emulator could not carry out. The program counter is
+/// left pointing at the faulting instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fault {
+    /// `2NNN` with all 16 stack entries in use.
+    StackOverflow,
+
+    /// `00EE` with nothing on the stack.
+    StackUnderflow,
+
+    /// A fetch or memory access past the end of memory.
+    MemoryOutOfBounds { addr: usize },
+
+    UnknownOpcode(u16),
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fault::StackOverflow => write!(f, "stack overflow"),
+            Fault::StackUnderflow => write!(f, "stack underflow"),
+            Fault::MemoryOutOfBounds { addr } => {
+                write!(f, "memory access out of bounds at 0x{:04X}", addr)
+            }
+            Fault::UnknownOpcode(opcode) => write!(f, "unknown opcode {:04X}", opcode),
+        }
+    }
+}
+
+impl Error for Fault {}
@@ -0,0 +1,345 @@
+use crate::emulator::{Emulator, HIRES_HEIGHT, HIRES_WIDTH};
+use crate::rng::Rng;
+use std::collections::{HashSet, VecDeque};
+
+/// Everything an instruction can change apart from memory and the display.
+#[derive(Clone, Debug)]
+struct CpuState {
+    v: [u8; 16],
+    i: u16,
+    pc: u16,
+    stack: [u16; 16],
+    sp: u8,
+    delay_timer: u8,
+    sound_timer: u8,
+    planes: u8,
+    hires: bool,
+    exited: bool,
+    rpl: [u8; 16],
+    audio_pattern: [u8; 16],
+    pitch: u8,
+    waiting_for_key: Option<u8>,
+    rng: u64,
+    vblank_wait: bool,
+
+    /// An instruction can end a frame, which ticks the timers and counts it.
+    frame_count: u64,
+}
+
+impl CpuState {
+    fn capture(emu: &Emulator) -> Self {
+        Self {
+            v: emu.v,
+            i: emu.i,
+            pc: emu.pc,
+            stack: emu.stack,
+            sp: emu.sp,
+            delay_timer: emu.delay_timer,
+            sound_timer: emu.sound_timer,
+            planes: emu.planes,
+            hires: emu.hires,
+            exited: emu.exited,
+            rpl: emu.rpl,
+            audio_pattern: emu.audio_pattern,
+            pitch: emu.pitch,
+            waiting_for_key: emu.waiting_for_key,
+            rng: emu.rng.state(),
+            vblank_wait: emu.vblank_wait,
+            frame_count: emu.frame_count,
+        }
+    }
+
+    fn restore(&self, emu: &mut Emulator) {
+        emu.v = self.v;
+        emu.i = self.i;
+        emu.pc = self.pc;
+        emu.stack = self.stack;
+        emu.sp = self.sp;
+        emu.delay_timer = self.delay_timer;
+        emu.sound_timer = self.sound_timer;
+        emu.planes = self.planes;
+        emu.hires = self.hires;
+        emu.exited = self.exited;
+        emu.rpl = self.rpl;
+        emu.audio_pattern = self.audio_pattern;
+        emu.pitch = self.pitch;
+        emu.waiting_for_key = self.waiting_for_key;
+        emu.rng = Rng::from_state(self.rng);
+        emu.vblank_wait = self.vblank_wait;
+        emu.frame_count = self.frame_count;
+    }
+}
+
+/// How to undo one executed instruction.
+#[derive(Clone, Debug)]
+struct Step {
+    before: CpuState,
+
+    /// Old values of the memory bytes written, in the order written.
+    memory: Vec<(usize, u8)>,
+
+    /// Old values of the display pixels that changed.
+    display: Vec<(u16, u8)>,
+}
+
+/// A bounded record of executed instructions that can be undone one at a
+/// time. A capacity of 0 (the default) disables recording.
+#[derive(Clone, Debug, Default)]
+pub struct History {
+    steps: VecDeque<Step>,
+    capacity: usize,
+
+    /// Instruction being recorded by [`History::begin`], if any.
+    pending: Option<Step>,
+    display_before: Option<Box<[u8; HIRES_WIDTH * HIRES_HEIGHT]>>,
+
+    /// Memory written by recorded instructions, or restored by stepping
+    /// back, since the last [`History::take_writes`].
+    written: HashSet<usize>,
+}
+
+impl History {
+    /// Keeps up to `capacity` instructions.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.steps.clear();
+        self.pending = None;
+        self.display_before = None;
+        self.written.clear();
+    }
+
+    /// Hands over the addresses written since the last call.
+    pub fn take_writes(&mut self) -> HashSet<usize> {
+        std::mem::take(&mut self.written)
+    }
+
+    /// Starts recording the instruction `opcode` that `emu` is about to run.
+    pub(crate) fn begin(emu: &mut Emulator, opcode: u16) {
+        if !emu.history.is_enabled() {
+            return;
+        }
+
+        let display_before = touches_display(opcode).then(|| Box::new(emu.display));
+        emu.history.pending = Some(Step {
+            before: CpuState::capture(emu),
+            memory: Vec::new(),
+            display: Vec::new(),
+        });
+        emu.history.display_before = display_before;
+    }
+
+    /// Notes a memory write made by the instruction being recorded.
+    pub(crate) fn note_write(&mut self, addr: usize, old: u8) {
+        if let Some(step) = self.pending.as_mut() {
+            step.memory.push((addr, old));
+            self.written.insert(addr);
+        }
+    }
+
+    /// Finishes recording once the instruction has run.
+    pub(crate) fn commit(emu: &mut Emulator) {
+        let Some(mut step) = emu.history.pending.take() else {
+            return;
+        };
+
+        if let Some(before) = emu.history.display_before.take() {
+            step.display = before
+                .iter()
+                .zip(emu.display.iter())
+                .enumerate()
+                .filter(|(_, (old, new))| old != new)
+                .map(|(index, (&old, _))| (index as u16, old))
+                .collect();
+        }
+
+        let history = &mut emu.history;
+        if history.steps.len() == history.capacity {
+            history.steps.pop_front();
+        }
+        history.steps.push_back(step);
+    }
+
+    /// Drops the instruction being recorded, e.g. because it faulted.
+    pub(crate) fn abort(&mut self) {
+        self.pending = None;
+        self.display_before = None;
+    }
+
+    /// Undoes the most recent instruction. Returns false if there is none.
+    pub fn step_back(emu: &mut Emulator) -> bool {
+        let Some(step) = emu.history.steps.pop_back() else {
+            return false;
+        };
+
+        for &(addr, old) in step.memory.iter().rev() {
+            if let Some(byte) = emu.memory.get_mut(addr) {
+                *byte = old;
+                emu.history.written.insert(addr);
+            }
+        }
+        for &(index, old) in &step.display {
+            emu.display[index as usize] = old;
+        }
+        step.before.restore(emu);
+        emu.instruction_count = emu.instruction_count.saturating_sub(1);
+        true
+    }
+}
+
+/// Whether an instruction can change the display: clears, scrolls,
+/// resolution switches and sprite draws.
+fn touches_display(opcode: u16) -> bool {
+    match opcode & 0xF000 {
+        0x0000 => matches!(opcode & 0xFFF0, 0x00C0 | 0x00D0)
+            || matches!(opcode, 0x00E0 | 0x00FB | 0x00FC | 0x00FE | 0x00FF),
+        0xD000 => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::breakpoint::{Breakpoint, Condition};
+    use crate::quirks::Platform;
+    use crate::savestate;
+
+    /// Draws a sprite, stores registers with `FX55` and rolls a random
+    /// number in a loop, setting the delay timer on the way in.
+    const PROGRAM: [u8; 20] = [
+        0x60, 0x05, // LD V0, 05
+        0xF0, 0x15, // LD DT, V0
+        0xA2, 0x14, // LD I, 0x214
+        0xD0, 0x15, // DRW V0, V1, 5
+        0xC0, 0xFF, // RND V0, FF
+        0xA3, 0x00, // LD I, 0x300
+        0xF3, 0x55, // LD [I], V3
+        0x71, 0x01, // ADD V1, 01
+        0x12, 0x04, // JP 0x204
+        0xF0, 0x90, // sprite
+    ];
+
+    /// Everything an instruction can change, for comparing two emulators.
+    fn snapshot(emu: &Emulator) -> (Vec<u8>, u64, u64) {
+        (savestate::encode(emu), emu.frame_count, emu.instruction_count)
+    }
+
+    fn recording() -> Emulator {
+        // The COSMAC VIP waits for vblank after drawing, so frames end early.
+        let mut emu = Emulator::with_platform(Platform::Chip8);
+        emu.set_seed(7);
+        emu.load_rom(&PROGRAM);
+        emu.history = History::new(1000);
+        emu
+    }
+
+    #[test]
+    fn stepping_back_every_instruction_restores_the_start() {
+        let mut emu = recording();
+        let start = snapshot(&emu);
+        for _ in 0..20 {
+            emu.run_frame([false; 16]).unwrap();
+        }
+        assert_eq!(emu.frame_count, 20);
+        assert!(emu.memory[0x300] != 0 || emu.memory[0x301] != 0);
+
+        let executed = emu.instruction_count as usize;
+        assert_eq!(emu.history.len(), executed);
+        for _ in 0..executed {
+            assert!(emu.step_back());
+        }
+        assert!(!emu.step_back());
+        assert_eq!(snapshot(&emu), start);
+    }
+
+    #[test]
+    fn each_step_back_undoes_exactly_one_instruction() {
+        let mut emu = recording();
+        let mut states = vec![snapshot(&emu)];
+        for _ in 0..30 {
+            emu.cycle().unwrap();
+            states.push(snapshot(&emu));
+        }
+        states.pop();
+        while let Some(state) = states.pop() {
+            assert!(emu.step_back());
+            assert_eq!(snapshot(&emu), state);
+        }
+    }
+
+    #[test]
+    fn oldest_instructions_are_dropped_first() {
+        let mut emu = recording();
+        emu.history = History::new(3);
+        for _ in 0..5 {
+            emu.cycle().unwrap();
+        }
+        assert_eq!(emu.history.len(), 3);
+        for _ in 0..3 {
+            assert!(emu.step_back());
+        }
+        assert!(!emu.step_back());
+        assert_eq!((emu.pc, emu.instruction_count), (0x204, 2));
+    }
+
+    #[test]
+    fn faulting_instructions_are_not_recorded() {
+        let mut emu = recording();
+        emu.load_rom(&[0x00, 0xEE]);
+        assert!(emu.cycle().is_err());
+        assert!(emu.history.is_empty());
+    }
+
+    #[test]
+    fn reverse_continue_stops_where_the_condition_held() {
+        let mut emu = recording();
+        for _ in 0..20 {
+            emu.run_frame([false; 16]).unwrap();
+        }
+        let breakpoint = Breakpoint {
+            condition: Some(Condition::parse("V1 == 3").unwrap()),
+            ..Default::default()
+        };
+        emu.breakpoints.insert(0x204, breakpoint);
+        assert_eq!(emu.reverse_continue(), Some(0x204));
+        assert_eq!((emu.pc, emu.v[1]), (0x204, 3));
+
+        emu.breakpoints.clear();
+        assert_eq!(emu.reverse_continue(), None);
+        assert_eq!((emu.pc, emu.instruction_count), (0x200, 0));
+    }
+
+    #[test]
+    fn writes_are_handed_over_once() {
+        let mut emu = recording();
+        for _ in 0..7 {
+            emu.cycle().unwrap();
+        }
+        let mut written: Vec<usize> = emu.history.take_writes().into_iter().collect();
+        written.sort();
+        assert_eq!(written, [0x300, 0x301, 0x302, 0x303]);
+        assert!(emu.history.take_writes().is_empty());
+
+        // Undoing the FX55 writes the old bytes back.
+        emu.step_back();
+        assert_eq!(emu.history.take_writes().len(), 4);
+    }
+}
@@ -0,0 +1,30 @@
+//! CHIP-8 emulator core.
+//!
+//! The [`Emulator`] can be driven headlessly one frame at a time with
+//! [`Emulator::run_frame`], or interactively through the terminal UI when the
+//! `tui` feature is enabled (the default).
+
+pub mod analysis;
+pub mod assembler;
+pub mod breakpoint;
+pub mod database;
+pub mod disassembler;
+pub mod emulator;
+pub mod expr;
+pub mod fault;
+pub mod history;
+pub mod octo;
+pub mod opcodes;
+pub mod quirks;
+pub mod rewind;
+pub mod rng;
+pub mod savestate;
+pub mod symbols;
+pub mod trace;
+#[cfg(feature = "tui")]
+pub mod ui;
+pub mod watchpoint;
+
+pub use emulator::{Emulator, FrameOutput, RunUntil, StopReason};
+pub use fault::Fault;
+pub use quirks::{Platform, Quirks};
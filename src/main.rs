@@ -1,33 +1,308 @@
-mod emulator;
-mod opcodes;
-mod ui;
-mod disassembler;
-
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use std::fs;
-use std::path::PathBuf;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use term_8::analysis::Analysis;
+use term_8::assembler;
+use term_8::breakpoint::Breakpoint;
+use term_8::database::Database;
+use term_8::emulator;
+use term_8::octo;
+use term_8::symbols::Symbols;
+use term_8::trace::{self, Trace, TraceFilter};
+use term_8::ui::{self, Loaded, Watch};
+use term_8::{Emulator, Platform};
 
 #[derive(Parser, Debug)]
 #[command(name = "term-8")]
 #[command(about = "A CHIP-8 emulator with interactive debugger", long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
-    rom_file: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[arg(required = true)]
+    rom_file: Option<PathBuf>,
+
+    /// Instructions per frame [default: from the ROM database, or 10]
+    #[arg(short, long)]
+    speed: Option<u32>,
+
+    /// Quirk profile to emulate: chip8, modern, chip48, schip or xochip
+    #[arg(long)]
+    platform: Option<Platform>,
+
+    /// ROM database (chip-8-database programs.json) [default: ~/.config/term-8/programs.json]
+    #[arg(long, value_name = "FILE")]
+    database: Option<PathBuf>,
+
+    /// Seed for the CXNN random number generator, for reproducible runs
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Label file (":label ADDR" or "ADDR label" lines) for the debugger
+    #[arg(long, value_name = "FILE")]
+    symbols: Option<PathBuf>,
+
+    /// Reload the ROM (or Octo source) whenever the file changes
+    #[arg(long)]
+    watch: bool,
+
+    /// Keep breakpoints and watchpoints set in the inspector across reloads
+    #[arg(long, requires = "watch")]
+    keep_breakpoints: bool,
+
+    /// Log every executed instruction to this file
+    #[arg(long, value_name = "FILE")]
+    trace: Option<PathBuf>,
+
+    /// Only trace instructions at these addresses, e.g. 0x200-0x2FF
+    #[arg(long, value_name = "START-END", value_parser = trace::parse_address_range, requires = "trace")]
+    trace_addr: Option<RangeInclusive<u16>>,
 
-    #[arg(short, long, default_value_t = 10)]
-    speed: u32,
+    /// Only trace instructions in these frames, e.g. 600-900 or 600-
+    #[arg(long, value_name = "START-END", value_parser = trace::parse_frame_range, requires = "trace")]
+    trace_frames: Option<RangeInclusive<u64>>,
+
+    /// Add a checksum of memory to each traced instruction (slow)
+    #[arg(long, requires = "trace")]
+    trace_memory: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Find the first instruction where two --trace logs disagree
+    Tracediff {
+        first: PathBuf,
+        second: PathBuf,
+
+        /// Lines of context to show around the divergence
+        #[arg(short = 'C', long, default_value_t = 3)]
+        context: usize,
+    },
+
+    /// Print an annotated assembly listing of a ROM
+    Disasm {
+        rom_file: PathBuf,
+
+        /// Label file to name addresses with
+        #[arg(long, value_name = "FILE")]
+        symbols: Option<PathBuf>,
+    },
+
+    /// Assemble a source file into a ROM
+    Asm {
+        source: PathBuf,
+
+        /// Where to write the ROM
+        #[arg(short, long, value_name = "ROM")]
+        output: PathBuf,
+    },
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
 
-    let rom_data = fs::read(&args.rom_file)?;
-    
-    let mut emulator = emulator::Emulator::new();
+    match &args.command {
+        Some(Command::Tracediff { first, second, context }) => return tracediff(first, second, *context),
+        Some(Command::Disasm { rom_file, symbols }) => return disasm(rom_file, symbols.as_deref()),
+        Some(Command::Asm { source, output }) => return asm(source, output),
+        None => {}
+    }
+
+    // Clap only lets this be missing when there is a subcommand.
+    let rom_file = args.rom_file.clone().expect("ROM file is required");
+    let database = load_database(args.database.as_deref())?;
+    let (emulator, symbols, info) = load(&args, &rom_file, &database)?;
+
+    let trace = match &args.trace {
+        Some(path) => {
+            let filter = TraceFilter {
+                addresses: args.trace_addr.clone(),
+                frames: args.trace_frames.clone(),
+            };
+            Some(Trace::create(path, filter, args.trace_memory)?)
+        }
+        None => None,
+    };
+
+    let watch = args.watch.then(|| {
+        let rom_file = rom_file.clone();
+        let keep_breakpoints = args.keep_breakpoints;
+        Watch {
+            load: Box::new(move || load(&args, &rom_file, &database)),
+            keep_breakpoints,
+        }
+    });
+
+    ui::run(emulator, &rom_file, trace, symbols, info, watch)?;
+
+    Ok(())
+}
+
+/// Builds an emulator for `rom_file` as configured on the command line,
+/// compiling it first if it is Octo source. Settings the command line leaves
+/// out come from the ROM's entry in `database`, if it has one.
+fn load(args: &Args, rom_file: &Path, database: &Database) -> Result<Loaded, String> {
+    let mut symbols = load_symbols(args.symbols.as_deref())?;
+    let mut breakpoints = Vec::new();
+    let rom_data = if rom_file.extension().is_some_and(|ext| ext == "8o") {
+        let source = fs::read_to_string(rom_file).map_err(|err| format!("{}: {}", rom_file.display(), err))?;
+        let program = octo::compile(&source)
+            .map_err(|err| format!("{}:{}: {}", rom_file.display(), err.line, err.message))?;
+        for (addr, name) in program.labels.iter() {
+            // A label from --symbols wins over the source's own.
+            let _ = symbols.insert(name, addr);
+        }
+        breakpoints = program.breakpoints;
+        program.rom
+    } else {
+        fs::read(rom_file).map_err(|err| format!("{}: {}", rom_file.display(), err))?
+    };
+
+    let info = database.get(&emulator::rom_sha1(&rom_data)).cloned();
+    let platform = args.platform.or(info.as_ref().and_then(|info| info.platform));
+    let mut emulator = match platform {
+        Some(platform) => Emulator::with_platform(platform),
+        None => Emulator::new(),
+    };
+    if args.platform.is_none() {
+        if let Some(quirks) = info.as_ref().and_then(|info| info.quirks) {
+            emulator.quirks = quirks;
+        }
+    }
+    if let Some(seed) = args.seed {
+        emulator.set_seed(seed);
+    }
     emulator.load_rom(&rom_data);
+    emulator.cycles_per_frame = args
+        .speed
+        .or(info.as_ref().and_then(|info| info.cycles_per_frame))
+        .unwrap_or(10);
+    for (name, addr) in breakpoints {
+        let breakpoint = Breakpoint {
+            name: Some(name),
+            ..Breakpoint::default()
+        };
+        emulator.breakpoints.insert(addr, breakpoint);
+    }
+
+    Ok((emulator, symbols, info))
+}
+
+/// Loads the ROM database from `path`, or from the default location if
+/// there is one there.
+fn load_database(path: Option<&Path>) -> Result<Database, String> {
+    if let Some(path) = path {
+        return Database::load(path);
+    }
+    let default = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .map(|config| config.join("term-8").join("programs.json"));
+    match default {
+        Some(path) if path.exists() => Database::load(&path),
+        _ => Ok(Database::default()),
+    }
+}
+
+fn load_symbols(path: Option<&Path>) -> Result<Symbols, String> {
+    match path {
+        Some(path) => Symbols::load(path).map_err(|err| format!("{}: {}", path.display(), err)),
+        None => Ok(Symbols::default()),
+    }
+}
 
-    ui::run(emulator, args.speed)?;
+/// Prints an annotated listing of a ROM, separating reachable code from
+/// data.
+fn disasm(rom_file: &Path, symbols: Option<&Path>) -> Result<(), Box<dyn std::error::Error>> {
+    let rom = fs::read(rom_file)?;
+    let symbols = load_symbols(symbols)?;
+    let analysis = Analysis::new(&rom);
 
+    println!("; {}", rom_file.display());
+    println!(
+        "; {} bytes: {} of code in {} instructions, {} of data",
+        rom.len(),
+        analysis.code_bytes(),
+        analysis.instructions().count(),
+        rom.len() - analysis.code_bytes()
+    );
+    println!();
+    print!("{}", analysis.listing(&symbols));
     Ok(())
 }
 
+/// Assembles `source` into `output`, printing every error with its line
+/// number and exiting with status 1 if there are any.
+fn asm(source: &Path, output: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let text = fs::read_to_string(source)?;
+    match assembler::assemble(&text) {
+        Ok(rom) => {
+            fs::write(output, &rom)?;
+            println!("Wrote {} bytes to {}", rom.len(), output.display());
+            Ok(())
+        }
+        Err(errors) => {
+            for error in &errors {
+                eprintln!("{}:{}: {}", source.display(), error.line, error.message);
+            }
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Prints where two traces first diverge, exiting with status 1 if they do.
+fn tracediff(first: &Path, second: &Path, context: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let a = fs::read_to_string(first)?;
+    let b = fs::read_to_string(second)?;
+    let a: Vec<&str> = a.lines().collect();
+    let b: Vec<&str> = b.lines().collect();
+    let names = [first.display().to_string(), second.display().to_string()];
 
+    let divergence = match trace::diff(&a, &b) {
+        Ok(Some(divergence)) => divergence,
+        Ok(None) => {
+            println!("Traces are identical ({} instructions)", a.len());
+            return Ok(());
+        }
+        Err(bad) => {
+            let message = format!("{}:{}: not a term-8 trace line: {}", names[bad.trace], bad.line, bad.reason);
+            return Err(message.into());
+        }
+    };
+
+    let line = divergence.line;
+    println!("{} and {} diverge at line {}", names[0], names[1], line + 1);
+    println!();
+
+    let start = line.saturating_sub(context);
+    for (number, text) in a.iter().enumerate().take(line).skip(start) {
+        println!("    {:>8} | {}", number + 1, text);
+    }
+    for (marker, trace) in [("-", &a), ("+", &b)] {
+        for (number, text) in trace.iter().enumerate().skip(line).take(context + 1) {
+            println!("  {} {:>8} | {}", marker, number + 1, text);
+        }
+        if line >= trace.len() {
+            println!("  {} (end of trace)", marker);
+        }
+    }
+    println!();
+
+    if divergence.differences.is_empty() {
+        let shorter = if a.len() < b.len() { &names[0] } else { &names[1] };
+        println!("{} ends after {} instructions", shorter, a.len().min(b.len()));
+    } else {
+        for difference in &divergence.differences {
+            println!("  {}", difference);
+        }
+        if line > 0 {
+            println!();
+            println!("The state differs after the instruction on line {}:", line);
+            println!("    {}", a[line - 1]);
+        }
+    }
+
+    std::process::exit(1);
+}
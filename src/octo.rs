@@ -0,0 +1,1054 @@
+//! A compiler for Octo, the assembly language most CHIP-8 homebrew is
+//! written in, so `.8o` sources can be loaded directly.
+//!
+//! ```text
+//! :const SPEED 2
+//! : main
+//!     va := 2
+//!     loop
+//!         va += SPEED
+//!         if va == 40 then va := 0
+//!     again
+//! ```
+//!
+//! Supported are all instructions (including SUPER-CHIP and XO-CHIP ones),
+//! labels, `if ... then`, `if ... begin ... else ... end`, `loop ... while
+//! ... again`, the `<`, `>`, `<=` and `>=` comparisons (which use VF),
+//! `:const`, `:alias`, `:calc`, `:macro`, `:org`, `:byte`, `:pointer`,
+//! `:unpack` and `:breakpoint`. Execution starts at `main`, reached through
+//! a jump at 0x200.
+
+use crate::symbols::Symbols;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+/// Where the program is loaded.
+const ORIGIN: usize = 0x200;
+
+/// Macro expansions allowed in one program, to stop runaway recursion.
+const MAX_EXPANSIONS: usize = 10_000;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OctoError {
+    /// One-based line number.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for OctoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for OctoError {}
+
+/// A compiled program.
+#[derive(Clone, Debug)]
+pub struct Program {
+    /// The ROM, to be loaded at 0x200.
+    pub rom: Vec<u8>,
+
+    /// Every label, for the debugger.
+    pub labels: Symbols,
+
+    /// Name and address of each `:breakpoint`.
+    pub breakpoints: Vec<(String, u16)>,
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    text: String,
+    line: usize,
+}
+
+/// A label used before it was defined, to be filled in at the end.
+struct Fixup {
+    at: usize,
+    name: String,
+    kind: FixupKind,
+    line: usize,
+}
+
+#[derive(Clone, Copy)]
+enum FixupKind {
+    /// The low 12 bits of the instruction at `at`.
+    Nnn,
+    /// The 16-bit word at `at`.
+    Long,
+    /// The low nibble of the byte at `at` gets bits 8-11.
+    High,
+    /// The byte at `at` gets bits 0-7.
+    Low,
+}
+
+struct Macro {
+    args: Vec<String>,
+    body: Vec<Token>,
+}
+
+/// An open `if ... begin` or `loop`.
+enum Block {
+    /// Address of the jump taken when the condition fails, or past `else`.
+    If { jump: usize, line: usize },
+    /// Where `again` jumps back to, and the jumps `while` adds to leave.
+    Loop { start: usize, exits: Vec<usize>, line: usize },
+}
+
+/// The skip instructions implementing a comparison.
+struct Condition {
+    /// Instructions to run first, for comparisons that go through VF.
+    setup: Vec<u16>,
+    /// Skips the next instruction when the comparison holds.
+    skip_if_true: u16,
+    /// Skips the next instruction when it does not.
+    skip_if_false: u16,
+}
+
+struct Compiler {
+    tokens: VecDeque<Token>,
+    line: usize,
+    here: usize,
+    rom: Vec<u8>,
+    written: Vec<bool>,
+    labels: HashMap<String, u16>,
+    constants: HashMap<String, f64>,
+    aliases: HashMap<String, u16>,
+    macros: HashMap<String, Macro>,
+    fixups: Vec<Fixup>,
+    blocks: Vec<Block>,
+    breakpoints: Vec<(String, u16)>,
+    expansions: usize,
+}
+
+/// Compiles Octo source into a ROM. Compilation stops at the first error.
+pub fn compile(source: &str) -> Result<Program, OctoError> {
+    let mut compiler = Compiler::new(source);
+    compiler.compile()?;
+
+    let mut labels = Symbols::default();
+    let mut names: Vec<_> = compiler.labels.iter().collect();
+    names.sort_by_key(|&(name, &addr)| (addr, name));
+    for (name, &addr) in names {
+        // Label names are unique already.
+        let _ = labels.insert(name, addr);
+    }
+
+    Ok(Program {
+        rom: compiler.rom,
+        labels,
+        breakpoints: compiler.breakpoints,
+    })
+}
+
+fn tokenize(source: &str) -> VecDeque<Token> {
+    let mut tokens = VecDeque::new();
+    for (index, line) in source.lines().enumerate() {
+        for word in line.split_whitespace() {
+            if word.starts_with('#') {
+                break;
+            }
+            tokens.push_back(Token {
+                text: word.to_string(),
+                line: index + 1,
+            });
+        }
+    }
+    tokens
+}
+
+fn parse_number(text: &str) -> Option<f64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let lower = digits.to_ascii_lowercase();
+    let value = if let Some(hex) = lower.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()? as f64
+    } else if let Some(binary) = lower.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2).ok()? as f64
+    } else if lower.starts_with(|c: char| c.is_ascii_digit()) {
+        lower.parse().ok()?
+    } else {
+        return None;
+    };
+    Some(if negative { -value } else { value })
+}
+
+/// `v0`-`vf`, in either case.
+fn register_name(text: &str) -> Option<u16> {
+    let index = text.strip_prefix(['v', 'V'])?;
+    if index.len() != 1 {
+        return None;
+    }
+    u16::from_str_radix(index, 16).ok()
+}
+
+impl Compiler {
+    fn new(source: &str) -> Self {
+        Self {
+            tokens: tokenize(source),
+            line: 1,
+            here: ORIGIN,
+            rom: Vec::new(),
+            written: Vec::new(),
+            labels: HashMap::new(),
+            constants: HashMap::new(),
+            aliases: HashMap::new(),
+            macros: HashMap::new(),
+            fixups: Vec::new(),
+            blocks: Vec::new(),
+            breakpoints: Vec::new(),
+            expansions: 0,
+        }
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, OctoError> {
+        Err(OctoError {
+            line: self.line,
+            message: message.into(),
+        })
+    }
+
+    fn compile(&mut self) -> Result<(), OctoError> {
+        // Room for the jump to main.
+        self.emit_op(0x1000)?;
+
+        while let Some(token) = self.next_token() {
+            self.statement(&token)?;
+        }
+
+        match self.blocks.pop() {
+            Some(Block::If { line, .. }) => {
+                self.line = line;
+                return self.error("'if ... begin' without 'end'");
+            }
+            Some(Block::Loop { line, .. }) => {
+                self.line = line;
+                return self.error("'loop' without 'again'");
+            }
+            None => {}
+        }
+
+        for fixup in std::mem::take(&mut self.fixups) {
+            self.line = fixup.line;
+            let Some(&addr) = self.labels.get(&fixup.name) else {
+                return self.error(format!("'{}' is not defined", fixup.name));
+            };
+            self.patch(fixup.at, fixup.kind, addr)?;
+        }
+
+        let Some(&main) = self.labels.get("main") else {
+            self.line = 1;
+            return self.error("the program has no 'main' label");
+        };
+        self.patch(ORIGIN, FixupKind::Nnn, main)
+    }
+
+    fn next_token(&mut self) -> Option<String> {
+        let token = self.tokens.pop_front()?;
+        self.line = token.line;
+        Some(token.text)
+    }
+
+    fn expect_token(&mut self, what: &str) -> Result<String, OctoError> {
+        match self.next_token() {
+            Some(token) => Ok(token),
+            None => self.error(format!("expected {} at the end of the file", what)),
+        }
+    }
+
+    fn expect(&mut self, text: &str) -> Result<(), OctoError> {
+        let token = self.expect_token(&format!("'{}'", text))?;
+        if token != text {
+            return self.error(format!("expected '{}', got '{}'", text, token));
+        }
+        Ok(())
+    }
+
+    fn peek(&self) -> Option<&str> {
+        self.tokens.front().map(|token| token.text.as_str())
+    }
+
+    fn emit(&mut self, byte: u8) -> Result<(), OctoError> {
+        if self.here >= 0x10000 {
+            return self.error("the program does not fit in memory");
+        }
+        let index = self.here - ORIGIN;
+        if index >= self.rom.len() {
+            self.rom.resize(index + 1, 0);
+            self.written.resize(index + 1, false);
+        }
+        if self.written[index] {
+            return self.error(format!("data overlaps at 0x{:04X}", self.here));
+        }
+        self.rom[index] = byte;
+        self.written[index] = true;
+        self.here += 1;
+        Ok(())
+    }
+
+    fn emit_op(&mut self, opcode: u16) -> Result<(), OctoError> {
+        let [high, low] = opcode.to_be_bytes();
+        self.emit(high)?;
+        self.emit(low)
+    }
+
+    fn patch(&mut self, at: usize, kind: FixupKind, value: u16) -> Result<(), OctoError> {
+        let index = at - ORIGIN;
+        match kind {
+            FixupKind::Nnn => {
+                if value > 0xFFF {
+                    return self.error(format!("address 0x{:04X} does not fit in 12 bits", value));
+                }
+                self.rom[index] = self.rom[index] & 0xF0 | (value >> 8) as u8;
+                self.rom[index + 1] = value as u8;
+            }
+            FixupKind::Long => {
+                self.rom[index..index + 2].copy_from_slice(&value.to_be_bytes());
+            }
+            FixupKind::High => self.rom[index] = self.rom[index] & 0xF0 | (value >> 8 & 0xF) as u8,
+            FixupKind::Low => self.rom[index] = value as u8,
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, token: &str) -> Result<(), OctoError> {
+        if let Some(reg) = self.register(token) {
+            return self.register_statement(reg);
+        }
+
+        match token {
+            ":" => {
+                let name = self.expect_token("a label name")?;
+                self.define_label(&name)
+            }
+            ":const" => {
+                let name = self.expect_token("a constant name")?;
+                let value = self.expect_token("a value")?;
+                let value = self.value(&value)?;
+                self.define(&name)?;
+                self.constants.insert(name, value);
+                Ok(())
+            }
+            ":alias" => {
+                let name = self.expect_token("an alias name")?;
+                let target = self.expect_token("a register")?;
+                let Some(reg) = self.register(&target) else {
+                    return self.error(format!("'{}' is not a register", target));
+                };
+                self.aliases.insert(name, reg);
+                Ok(())
+            }
+            ":calc" => {
+                let name = self.expect_token("a constant name")?;
+                let value = self.calc_block()?;
+                self.define(&name)?;
+                self.constants.insert(name, value);
+                Ok(())
+            }
+            ":macro" => self.define_macro(),
+            ":org" => {
+                let addr = self.expect_token("an address")?;
+                let addr = self.value(&addr)? as i64;
+                if !(ORIGIN as i64..0x10000).contains(&addr) {
+                    return self.error(format!(":org address 0x{:X} is outside the program", addr));
+                }
+                self.here = addr as usize;
+                Ok(())
+            }
+            ":breakpoint" => {
+                let name = self.expect_token("a breakpoint name")?;
+                self.breakpoints.push((name, self.here as u16));
+                Ok(())
+            }
+            ":monitor" => {
+                self.expect_token("an address")?;
+                self.expect_token("a length or format")?;
+                Ok(())
+            }
+            ":byte" => {
+                let value = if self.peek() == Some("{") {
+                    self.calc_block()?
+                } else {
+                    let token = self.expect_token("a value")?;
+                    self.value(&token)?
+                };
+                let byte = self.byte_value(value, ":byte")?;
+                self.emit(byte)
+            }
+            ":pointer" => {
+                let token = self.expect_token("an address")?;
+                let addr = self.address(&token, FixupKind::Long, self.here, 0xFFFF)?;
+                self.emit_op(addr)
+            }
+            ":unpack" => {
+                let nibble = self.expect_token("a nibble")?;
+                let nibble = self.value(&nibble)? as i64;
+                let token = self.expect_token("a label")?;
+                if !(0..=0xF).contains(&nibble) {
+                    return self.error(format!(":unpack nibble {} is out of range", nibble));
+                }
+                let addr = self.address(&token, FixupKind::High, self.here + 1, 0xFFF)?;
+                self.emit_op(0x6000 | (nibble as u16) << 4 | addr >> 8)?;
+                self.address(&token, FixupKind::Low, self.here + 1, 0xFFF)?;
+                self.emit_op(0x6100 | addr & 0xFF)
+            }
+            ":next" | ":stringmode" | ":assert" | ":call" | ":proto" => {
+                self.error(format!("{} is not supported", token))
+            }
+
+            "return" | ";" => self.emit_op(0x00EE),
+            "clear" => self.emit_op(0x00E0),
+            "hires" => self.emit_op(0x00FF),
+            "lores" => self.emit_op(0x00FE),
+            "exit" => self.emit_op(0x00FD),
+            "scroll-left" => self.emit_op(0x00FC),
+            "scroll-right" => self.emit_op(0x00FB),
+            "scroll-down" => {
+                let n = self.nibble_operand()?;
+                self.emit_op(0x00C0 | n)
+            }
+            "scroll-up" => {
+                let n = self.nibble_operand()?;
+                self.emit_op(0x00D0 | n)
+            }
+            "audio" => self.emit_op(0xF002),
+            "plane" => {
+                let n = self.nibble_operand()?;
+                self.emit_op(0xF001 | n << 8)
+            }
+            "bcd" => {
+                let x = self.register_operand()?;
+                self.emit_op(0xF033 | x << 8)
+            }
+            "saveflags" => {
+                let x = self.register_operand()?;
+                self.emit_op(0xF075 | x << 8)
+            }
+            "loadflags" => {
+                let x = self.register_operand()?;
+                self.emit_op(0xF085 | x << 8)
+            }
+            "save" | "load" => {
+                let x = self.register_operand()?;
+                if self.peek() == Some("-") {
+                    self.next_token();
+                    let y = self.register_operand()?;
+                    let op = if token == "save" { 0x5002 } else { 0x5003 };
+                    self.emit_op(op | x << 8 | y << 4)
+                } else {
+                    let op = if token == "save" { 0xF055 } else { 0xF065 };
+                    self.emit_op(op | x << 8)
+                }
+            }
+            "sprite" => {
+                let x = self.register_operand()?;
+                let y = self.register_operand()?;
+                let n = self.nibble_operand()?;
+                self.emit_op(0xD000 | x << 8 | y << 4 | n)
+            }
+            "jump" | "jump0" | "native" => {
+                let target = self.expect_token("an address")?;
+                let addr = self.address(&target, FixupKind::Nnn, self.here, 0xFFF)?;
+                let op = match token {
+                    "jump" => 0x1000,
+                    "jump0" => 0xB000,
+                    _ => 0x0000,
+                };
+                self.emit_op(op | addr)
+            }
+            "i" => self.i_statement(),
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let x = self.register_operand()?;
+                let op = match token {
+                    "delay" => 0xF015,
+                    "buzzer" => 0xF018,
+                    _ => 0xF03A,
+                };
+                self.emit_op(op | x << 8)
+            }
+
+            "if" => self.if_statement(),
+            "else" => {
+                let Some(Block::If { jump, line }) = self.blocks.pop() else {
+                    return self.error("'else' without 'if ... begin'");
+                };
+                let skip = self.here;
+                self.emit_op(0x1000)?;
+                let here = self.here as u16;
+                self.patch(jump, FixupKind::Nnn, here)?;
+                self.blocks.push(Block::If { jump: skip, line });
+                Ok(())
+            }
+            "end" => {
+                let Some(Block::If { jump, .. }) = self.blocks.pop() else {
+                    return self.error("'end' without 'if ... begin'");
+                };
+                let here = self.here as u16;
+                self.patch(jump, FixupKind::Nnn, here)
+            }
+            "loop" => {
+                self.blocks.push(Block::Loop {
+                    start: self.here,
+                    exits: Vec::new(),
+                    line: self.line,
+                });
+                Ok(())
+            }
+            "while" => {
+                if !self.blocks.iter().any(|block| matches!(block, Block::Loop { .. })) {
+                    return self.error("'while' outside of a loop");
+                }
+                let condition = self.condition()?;
+                for op in condition.setup {
+                    self.emit_op(op)?;
+                }
+                self.emit_op(condition.skip_if_true)?;
+                let exit = self.here;
+                self.emit_op(0x1000)?;
+                for block in self.blocks.iter_mut().rev() {
+                    if let Block::Loop { exits, .. } = block {
+                        exits.push(exit);
+                        break;
+                    }
+                }
+                Ok(())
+            }
+            "again" => {
+                let Some(Block::Loop { start, exits, .. }) = self.blocks.pop() else {
+                    return self.error("'again' without 'loop'");
+                };
+                self.emit_op(0x1000 | start as u16)?;
+                let here = self.here as u16;
+                for exit in exits {
+                    self.patch(exit, FixupKind::Nnn, here)?;
+                }
+                Ok(())
+            }
+
+            _ if self.macros.contains_key(token) => self.expand_macro(token),
+            _ => {
+                if let Some(value) = parse_number(token).or_else(|| self.constants.get(token).copied()) {
+                    let byte = self.byte_value(value, token)?;
+                    return self.emit(byte);
+                }
+                // Any other name calls the subroutine with that label.
+                let addr = self.address(token, FixupKind::Nnn, self.here, 0xFFF)?;
+                self.emit_op(0x2000 | addr)
+            }
+        }
+    }
+
+    fn define(&self, name: &str) -> Result<(), OctoError> {
+        if parse_number(name).is_some() || self.register(name).is_some() {
+            return self.error(format!("'{}' cannot be used as a name", name));
+        }
+        if self.labels.contains_key(name) || self.constants.contains_key(name) {
+            return self.error(format!("'{}' is already defined", name));
+        }
+        Ok(())
+    }
+
+    fn define_label(&mut self, name: &str) -> Result<(), OctoError> {
+        self.define(name)?;
+        if self.here > 0xFFFF {
+            return self.error("label is outside of memory");
+        }
+        self.labels.insert(name.to_string(), self.here as u16);
+        Ok(())
+    }
+
+    fn define_macro(&mut self) -> Result<(), OctoError> {
+        let name = self.expect_token("a macro name")?;
+        let mut args = Vec::new();
+        loop {
+            let token = self.expect_token("'{'")?;
+            if token == "{" {
+                break;
+            }
+            args.push(token);
+        }
+
+        let mut body = Vec::new();
+        let mut depth = 1;
+        loop {
+            let Some(token) = self.tokens.pop_front() else {
+                return self.error(format!("macro '{}' has no closing '}}'", name));
+            };
+            match token.text.as_str() {
+                "{" => depth += 1,
+                "}" => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                _ => {}
+            }
+            body.push(token);
+        }
+
+        self.macros.insert(name, Macro { args, body });
+        Ok(())
+    }
+
+    fn expand_macro(&mut self, name: &str) -> Result<(), OctoError> {
+        self.expansions += 1;
+        if self.expansions > MAX_EXPANSIONS {
+            return self.error(format!("too many macro expansions, is '{}' recursive?", name));
+        }
+
+        let arg_count = self.macros[name].args.len();
+        let mut values = HashMap::new();
+        for index in 0..arg_count {
+            let value = self.expect_token("a macro argument")?;
+            values.insert(self.macros[name].args[index].clone(), value);
+        }
+
+        let line = self.line;
+        for token in self.macros[name].body.iter().rev() {
+            let text = values.get(&token.text).unwrap_or(&token.text).clone();
+            self.tokens.push_front(Token { text, line });
+        }
+        Ok(())
+    }
+
+    /// A register name or alias.
+    fn register(&self, text: &str) -> Option<u16> {
+        register_name(text).or_else(|| self.aliases.get(text).copied())
+    }
+
+    fn register_operand(&mut self) -> Result<u16, OctoError> {
+        let token = self.expect_token("a register")?;
+        match self.register(&token) {
+            Some(reg) => Ok(reg),
+            None => self.error(format!("expected a register, got '{}'", token)),
+        }
+    }
+
+    fn nibble_operand(&mut self) -> Result<u16, OctoError> {
+        let token = self.expect_token("a number")?;
+        let value = self.value(&token)? as i64;
+        if !(0..=0xF).contains(&value) {
+            return self.error(format!("{} does not fit in 4 bits", token));
+        }
+        Ok(value as u16)
+    }
+
+    fn byte_value(&self, value: f64, text: &str) -> Result<u8, OctoError> {
+        let value = value as i64;
+        if !(-128..=255).contains(&value) {
+            return self.error(format!("{} does not fit in a byte", text));
+        }
+        Ok(value as u8)
+    }
+
+    /// A number, constant or defined label.
+    fn value(&self, token: &str) -> Result<f64, OctoError> {
+        if let Some(value) = parse_number(token) {
+            return Ok(value);
+        }
+        if let Some(&value) = self.constants.get(token) {
+            return Ok(value);
+        }
+        if let Some(&addr) = self.labels.get(token) {
+            return Ok(addr as f64);
+        }
+        self.error(format!("'{}' is not defined", token))
+    }
+
+    /// The address `token` stands for, or 0 with a fixup at `at` if it is a
+    /// label that is not defined yet.
+    fn address(&mut self, token: &str, kind: FixupKind, at: usize, max: i64) -> Result<u16, OctoError> {
+        let is_name = parse_number(token).is_none() && !self.constants.contains_key(token);
+        if is_name && !self.labels.contains_key(token) {
+            self.fixups.push(Fixup {
+                at,
+                name: token.to_string(),
+                kind,
+                line: self.line,
+            });
+            return Ok(0);
+        }
+        let value = self.value(token)? as i64;
+        if !(0..=max).contains(&value) {
+            return self.error(format!("address {} is out of range", token));
+        }
+        Ok(value as u16)
+    }
+
+    fn register_statement(&mut self, x: u16) -> Result<(), OctoError> {
+        let op = self.expect_token("an operator")?;
+        let operand = self.expect_token("an operand")?;
+        let y = self.register(&operand);
+
+        let opcode = match (op.as_str(), y) {
+            (":=", _) if operand == "random" => {
+                let mask = self.expect_token("a mask")?;
+                let mask = self.value(&mask)?;
+                0xC000 | x << 8 | self.byte_value(mask, "random mask")? as u16
+            }
+            (":=", _) if operand == "key" => 0xF00A | x << 8,
+            (":=", _) if operand == "delay" => 0xF007 | x << 8,
+            (":=", Some(y)) => 0x8000 | x << 8 | y << 4,
+            (":=", None) => {
+                let value = self.value(&operand)?;
+                0x6000 | x << 8 | self.byte_value(value, &operand)? as u16
+            }
+            ("+=", Some(y)) => 0x8004 | x << 8 | y << 4,
+            ("+=", None) => {
+                let value = self.value(&operand)?;
+                0x7000 | x << 8 | self.byte_value(value, &operand)? as u16
+            }
+            ("-=", Some(y)) => 0x8005 | x << 8 | y << 4,
+            ("-=", None) => {
+                let value = self.value(&operand)?;
+                0x7000 | x << 8 | self.byte_value(value, &operand)?.wrapping_neg() as u16
+            }
+            ("=-", Some(y)) => 0x8007 | x << 8 | y << 4,
+            ("|=", Some(y)) => 0x8001 | x << 8 | y << 4,
+            ("&=", Some(y)) => 0x8002 | x << 8 | y << 4,
+            ("^=", Some(y)) => 0x8003 | x << 8 | y << 4,
+            (">>=", Some(y)) => 0x8006 | x << 8 | y << 4,
+            ("<<=", Some(y)) => 0x800E | x << 8 | y << 4,
+            ("=-" | "|=" | "&=" | "^=" | ">>=" | "<<=", None) => {
+                return self.error(format!("'{}' needs a register, got '{}'", op, operand));
+            }
+            _ => return self.error(format!("unknown operator '{}'", op)),
+        };
+        self.emit_op(opcode)
+    }
+
+    fn i_statement(&mut self) -> Result<(), OctoError> {
+        let op = self.expect_token("an operator")?;
+        match op.as_str() {
+            ":=" => {
+                let operand = self.expect_token("an operand")?;
+                match operand.as_str() {
+                    "long" => {
+                        let target = self.expect_token("an address")?;
+                        self.emit_op(0xF000)?;
+                        let addr = self.address(&target, FixupKind::Long, self.here, 0xFFFF)?;
+                        self.emit_op(addr)
+                    }
+                    "hex" => {
+                        let x = self.register_operand()?;
+                        self.emit_op(0xF029 | x << 8)
+                    }
+                    "bighex" => {
+                        let x = self.register_operand()?;
+                        self.emit_op(0xF030 | x << 8)
+                    }
+                    _ => {
+                        let addr = self.address(&operand, FixupKind::Nnn, self.here, 0xFFF)?;
+                        self.emit_op(0xA000 | addr)
+                    }
+                }
+            }
+            "+=" => {
+                let x = self.register_operand()?;
+                self.emit_op(0xF01E | x << 8)
+            }
+            _ => self.error(format!("unknown operator 'i {}'", op)),
+        }
+    }
+
+    fn if_statement(&mut self) -> Result<(), OctoError> {
+        let line = self.line;
+        let condition = self.condition()?;
+        for &op in &condition.setup {
+            self.emit_op(op)?;
+        }
+
+        let keyword = self.expect_token("'then' or 'begin'")?;
+        match keyword.as_str() {
+            // The next statement runs only if the condition holds.
+            "then" => self.emit_op(condition.skip_if_false),
+            "begin" => {
+                self.emit_op(condition.skip_if_true)?;
+                let jump = self.here;
+                self.emit_op(0x1000)?;
+                self.blocks.push(Block::If { jump, line });
+                Ok(())
+            }
+            _ => self.error(format!("expected 'then' or 'begin', got '{}'", keyword)),
+        }
+    }
+
+    fn condition(&mut self) -> Result<Condition, OctoError> {
+        let x = self.register_operand()?;
+        let op = self.expect_token("a comparison")?;
+
+        let skips = |skip_if_true, skip_if_false| Condition {
+            setup: Vec::new(),
+            skip_if_true,
+            skip_if_false,
+        };
+        match op.as_str() {
+            "key" => return Ok(skips(0xE09E | x << 8, 0xE0A1 | x << 8)),
+            "-key" => return Ok(skips(0xE0A1 | x << 8, 0xE09E | x << 8)),
+            _ => {}
+        }
+
+        let operand = self.expect_token("an operand")?;
+        let y = self.register(&operand);
+        let immediate = match y {
+            Some(_) => 0,
+            None => {
+                let value = self.value(&operand)?;
+                self.byte_value(value, &operand)? as u16
+            }
+        };
+
+        // The relational comparisons leave VF = 1 when VX >= the operand
+        // (subtracting VX from it) or when the operand >= VX.
+        let load_vf = match y {
+            Some(y) => 0x8F00 | y << 4,
+            None => 0x6F00 | immediate,
+        };
+        let through_vf = |subtract: u16, vf: u16| Condition {
+            setup: vec![load_vf, subtract | x << 4],
+            skip_if_true: 0x3F00 | vf,
+            skip_if_false: 0x4F00 | vf,
+        };
+
+        Ok(match (op.as_str(), y) {
+            ("==", Some(y)) => skips(0x5000 | x << 8 | y << 4, 0x9000 | x << 8 | y << 4),
+            ("==", None) => skips(0x3000 | x << 8 | immediate, 0x4000 | x << 8 | immediate),
+            ("!=", Some(y)) => skips(0x9000 | x << 8 | y << 4, 0x5000 | x << 8 | y << 4),
+            ("!=", None) => skips(0x4000 | x << 8 | immediate, 0x3000 | x << 8 | immediate),
+            (">=", _) => through_vf(0x8F07, 1),
+            ("<", _) => through_vf(0x8F07, 0),
+            ("<=", _) => through_vf(0x8F05, 1),
+            (">", _) => through_vf(0x8F05, 0),
+            _ => return self.error(format!("unknown comparison '{}'", op)),
+        })
+    }
+
+    /// Evaluates `{ expression }`.
+    fn calc_block(&mut self) -> Result<f64, OctoError> {
+        self.expect("{")?;
+        let mut tokens = Vec::new();
+        loop {
+            let token = self.expect_token("'}'")?;
+            if token == "}" {
+                break;
+            }
+            tokens.push(token);
+        }
+        let mut pos = 0;
+        let value = self.calc(&tokens, &mut pos)?;
+        if pos != tokens.len() {
+            return self.error(format!("unexpected '{}' in expression", tokens[pos]));
+        }
+        Ok(value)
+    }
+
+    /// Octo expressions have no precedence and evaluate right to left, so
+    /// `2 * 3 + 1` is 8.
+    fn calc(&self, tokens: &[String], pos: &mut usize) -> Result<f64, OctoError> {
+        let left = self.calc_term(tokens, pos)?;
+        let Some(op) = tokens.get(*pos).filter(|&op| op != ")") else {
+            return Ok(left);
+        };
+        *pos += 1;
+        let right = self.calc(tokens, pos)?;
+        let (a, b) = (left as i64, right as i64);
+        Ok(match op.as_str() {
+            "+" => left + right,
+            "-" => left - right,
+            "*" => left * right,
+            "/" => left / right,
+            "%" => left % right,
+            "&" => (a & b) as f64,
+            "|" => (a | b) as f64,
+            "^" => (a ^ b) as f64,
+            "<<" => (a << b) as f64,
+            ">>" => (a >> b) as f64,
+            "pow" => left.powf(right),
+            "min" => left.min(right),
+            "max" => left.max(right),
+            "<" => (left < right) as i64 as f64,
+            ">" => (left > right) as i64 as f64,
+            "<=" => (left <= right) as i64 as f64,
+            ">=" => (left >= right) as i64 as f64,
+            "==" => (left == right) as i64 as f64,
+            "!=" => (left != right) as i64 as f64,
+            _ => return self.error(format!("unknown operator '{}' in expression", op)),
+        })
+    }
+
+    fn calc_term(&self, tokens: &[String], pos: &mut usize) -> Result<f64, OctoError> {
+        let Some(token) = tokens.get(*pos) else {
+            return self.error("expression ends early");
+        };
+        *pos += 1;
+
+        let unary: Option<fn(f64) -> f64> = match token.as_str() {
+            "-" => Some(|v| -v),
+            "~" => Some(|v| !(v as i64) as f64),
+            "!" => Some(|v| (v == 0.0) as i64 as f64),
+            "abs" => Some(f64::abs),
+            "sqrt" => Some(f64::sqrt),
+            "sin" => Some(f64::sin),
+            "cos" => Some(f64::cos),
+            "floor" => Some(f64::floor),
+            "ceil" => Some(f64::ceil),
+            _ => None,
+        };
+        if let Some(f) = unary {
+            return Ok(f(self.calc_term(tokens, pos)?));
+        }
+
+        match token.as_str() {
+            "(" => {
+                let value = self.calc(tokens, pos)?;
+                if tokens.get(*pos).map(String::as_str) != Some(")") {
+                    return self.error("missing ')' in expression");
+                }
+                *pos += 1;
+                Ok(value)
+            }
+            "HERE" => Ok(self.here as f64),
+            "PI" => Ok(std::f64::consts::PI),
+            "E" => Ok(std::f64::consts::E),
+            _ => self.value(token),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rom(source: &str) -> Vec<u8> {
+        compile(source).unwrap_or_else(|error| panic!("{}", error)).rom
+    }
+
+    fn error(source: &str) -> (usize, String) {
+        let error = compile(source).unwrap_err();
+        (error.line, error.message)
+    }
+
+    #[test]
+    fn main_is_reached_through_a_jump() {
+        assert_eq!(rom(": main\n  v0 := 0x12\n"), [0x12, 0x02, 0x60, 0x12]);
+        assert_eq!(rom(": helper\n  return\n: main\n  helper\n"), [0x12, 0x04, 0x00, 0xEE, 0x22, 0x02]);
+    }
+
+    #[test]
+    fn register_statements() {
+        let source = "
+: main
+  va := 2
+  va += 40
+  va -= vb
+  va =- vb
+  va |= vb
+  va &= vb
+  va ^= vb
+  va >>= vb
+  va <<= vb
+  va := random 0x0F
+  va := key
+  va := delay
+  delay := va
+  buzzer := va
+  i := hex va
+  i += va
+  bcd va
+  save va
+  load va
+  sprite va vb 5
+";
+        assert_eq!(
+            rom(source),
+            [
+                0x12, 0x02, 0x6A, 0x02, 0x7A, 0x28, 0x8A, 0xB5, 0x8A, 0xB7, 0x8A, 0xB1, 0x8A, 0xB2, 0x8A, 0xB3,
+                0x8A, 0xB6, 0x8A, 0xBE, 0xCA, 0x0F, 0xFA, 0x0A, 0xFA, 0x07, 0xFA, 0x15, 0xFA, 0x18, 0xFA, 0x29,
+                0xFA, 0x1E, 0xFA, 0x33, 0xFA, 0x55, 0xFA, 0x65, 0xDA, 0xB5,
+            ]
+        );
+    }
+
+    #[test]
+    fn if_then_skips_the_next_statement() {
+        let source = ": main\n  if va == 40 then va := 0\n  if va != vb then va := 1\n";
+        assert_eq!(rom(source), [0x12, 0x02, 0x4A, 0x28, 0x6A, 0x00, 0x5A, 0xB0, 0x6A, 0x01]);
+    }
+
+    #[test]
+    fn relational_comparisons_go_through_vf() {
+        let source = ": main\n  if v1 > 3 then v2 := 0\n";
+        assert_eq!(rom(source), [0x12, 0x02, 0x6F, 0x03, 0x8F, 0x15, 0x4F, 0x00, 0x62, 0x00]);
+    }
+
+    #[test]
+    fn if_begin_else_end() {
+        let source = "
+: main
+  if v1 != v2 begin
+    v3 := 1
+  else
+    v3 := 2
+  end
+";
+        assert_eq!(rom(source), [0x12, 0x02, 0x91, 0x20, 0x12, 0x0A, 0x63, 0x01, 0x12, 0x0C, 0x63, 0x02]);
+    }
+
+    #[test]
+    fn loop_while_again() {
+        let source = "
+: main
+  loop
+    v0 += 1
+    while v0 != 5
+  again
+";
+        assert_eq!(rom(source), [0x12, 0x02, 0x70, 0x01, 0x40, 0x05, 0x12, 0x0A, 0x12, 0x02]);
+    }
+
+    #[test]
+    fn forward_labels_are_fixed_up() {
+        let source = "
+: main
+  i := data
+  sprite v0 v1 3
+  jump done
+: data
+  0x80 0x40 0x20
+: done
+  return
+";
+        let program = compile(source).unwrap();
+        assert_eq!(
+            program.rom,
+            [0x12, 0x02, 0xA2, 0x08, 0xD0, 0x13, 0x12, 0x0B, 0x80, 0x40, 0x20, 0x00, 0xEE]
+        );
+        assert_eq!(program.labels.address("data"), Some(0x208));
+        assert_eq!(program.labels.address("done"), Some(0x20B));
+    }
+
+    #[test]
+    fn constants_and_calc() {
+        let source = ":const SPEED 3\n:calc DOUBLE { SPEED * 2 }\n: main\n  v0 := DOUBLE\n  v1 += SPEED\n";
+        assert_eq!(rom(source), [0x12, 0x02, 0x60, 0x06, 0x71, 0x03]);
+    }
+
+    #[test]
+    fn breakpoints_are_recorded_at_the_next_instruction() {
+        let source = ": main\n  v0 := 1\n  :breakpoint hit\n  v0 += 1\n  :breakpoint done\n";
+        let program = compile(source).unwrap();
+        assert_eq!(program.breakpoints, [("hit".to_string(), 0x204), ("done".to_string(), 0x206)]);
+    }
+
+    #[test]
+    fn errors_carry_their_line() {
+        assert_eq!(error(": main\n  jump nowhere\n"), (2, "'nowhere' is not defined".to_string()));
+        assert_eq!(error(": main\n  v0 := 256\n"), (2, "256 does not fit in a byte".to_string()));
+        assert_eq!(error(": main\n  else\n"), (2, "'else' without 'if ... begin'".to_string()));
+        assert_eq!(error(": main\n\n  again\n"), (3, "'again' without 'loop'".to_string()));
+    }
+}
@@ -1,24 +1,70 @@
-use crate::emulator::Emulator;
-use rand::Rng;
+use crate::emulator::{Emulator, BIG_FONT_ADDR};
+use crate::fault::Fault;
 
-pub fn execute(emu: &mut Emulator, opcode: u16) {
+/// Executes one instruction. The SUPER-CHIP and XO-CHIP extensions are
+/// always available, whatever quirks the emulator runs with; only memory
+/// size and the quirks themselves depend on the platform.
+pub fn execute(emu: &mut Emulator, opcode: u16) -> Result<(), Fault> {
     let x = ((opcode & 0x0F00) >> 8) as usize;
     let y = ((opcode & 0x00F0) >> 4) as usize;
     let n = (opcode & 0x000F) as u8;
     let nn = (opcode & 0x00FF) as u8;
     let nnn = opcode & 0x0FFF;
 
+    // Everything but jumps, calls, returns and exit moves on to the next
+    // instruction, which has to fit in the 16-bit address space. Checking
+    // first leaves the emulator untouched when it does not.
+    let moves_on = !matches!(opcode & 0xF000, 0x1000 | 0x2000 | 0xB000) && !matches!(opcode, 0x00EE | 0x00FD);
+    if moves_on {
+        next_pc(emu.pc, if opcode == 0xF000 { 4 } else { 2 })?;
+    }
+
     match opcode & 0xF000 {
         0x0000 => match opcode {
             0x00E0 => {
-                emu.display = [false; 64 * 32];
+                let planes = emu.planes;
+                for pixel in emu.display.iter_mut() {
+                    *pixel &= !planes;
+                }
                 emu.pc += 2;
             }
             0x00EE => {
+                if emu.sp == 0 {
+                    return Err(Fault::StackUnderflow);
+                }
+                let addr = next_pc(emu.stack[emu.sp as usize - 1], 2)?;
                 emu.sp -= 1;
-                emu.pc = emu.stack[emu.sp as usize];
+                emu.pc = addr;
+            }
+            0x00D0..=0x00DF => {
+                scroll(emu, 0, -(n as isize));
                 emu.pc += 2;
             }
+            0x00C0..=0x00CF => {
+                scroll(emu, 0, n as isize);
+                emu.pc += 2;
+            }
+            0x00FB => {
+                scroll(emu, 4, 0);
+                emu.pc += 2;
+            }
+            0x00FC => {
+                scroll(emu, -4, 0);
+                emu.pc += 2;
+            }
+            0x00FD => {
+                emu.exited = true;
+            }
+            0x00FE => {
+                emu.set_hires(false);
+                emu.pc += 2;
+            }
+            0x00FF => {
+                emu.set_hires(true);
+                emu.pc += 2;
+            }
+            // 0NNN calls a native machine routine on the original hardware,
+            // which nothing can emulate; ignore it like most interpreters.
             _ => {
                 emu.pc += 2;
             }
@@ -27,31 +73,40 @@ pub fn execute(emu: &mut Emulator, opcode: u16) {
             emu.pc = nnn;
         }
         0x2000 => {
+            if emu.sp as usize >= emu.stack.len() {
+                return Err(Fault::StackOverflow);
+            }
             emu.stack[emu.sp as usize] = emu.pc;
             emu.sp += 1;
             emu.pc = nnn;
         }
         0x3000 => {
-            if emu.v[x] == nn {
-                emu.pc += 4;
-            } else {
-                emu.pc += 2;
-            }
+            skip_if(emu, emu.v[x] == nn)?;
         }
         0x4000 => {
-            if emu.v[x] != nn {
-                emu.pc += 4;
-            } else {
+            skip_if(emu, emu.v[x] != nn)?;
+        }
+        0x5000 => match n {
+            0x0 => {
+                skip_if(emu, emu.v[x] == emu.v[y])?;
+            }
+            0x2 => {
+                // XO-CHIP allows the range to run in either direction.
+                emu.check_range(emu.i as usize, x.abs_diff(y) + 1)?;
+                for (offset, reg) in register_range(x, y).enumerate() {
+                    emu.write_byte(emu.i as usize + offset, emu.v[reg])?;
+                }
                 emu.pc += 2;
             }
-        }
-        0x5000 => {
-            if emu.v[x] == emu.v[y] {
-                emu.pc += 4;
-            } else {
+            0x3 => {
+                emu.check_range(emu.i as usize, x.abs_diff(y) + 1)?;
+                for (offset, reg) in register_range(x, y).enumerate() {
+                    emu.v[reg] = emu.read_byte(emu.i as usize + offset)?;
+                }
                 emu.pc += 2;
             }
-        }
+            _ => return Err(Fault::UnknownOpcode(opcode)),
+        },
         0x6000 => {
             emu.v[x] = nn;
             emu.pc += 2;
@@ -68,14 +123,23 @@ pub fn execute(emu: &mut Emulator, opcode: u16) {
                 }
                 0x1 => {
                     emu.v[x] |= emu.v[y];
+                    if emu.quirks.vf_reset {
+                        emu.v[0xF] = 0;
+                    }
                     emu.pc += 2;
                 }
                 0x2 => {
                     emu.v[x] &= emu.v[y];
+                    if emu.quirks.vf_reset {
+                        emu.v[0xF] = 0;
+                    }
                     emu.pc += 2;
                 }
                 0x3 => {
                     emu.v[x] ^= emu.v[y];
+                    if emu.quirks.vf_reset {
+                        emu.v[0xF] = 0;
+                    }
                     emu.pc += 2;
                 }
                 0x4 => {
@@ -91,8 +155,9 @@ pub fn execute(emu: &mut Emulator, opcode: u16) {
                     emu.pc += 2;
                 }
                 0x6 => {
-                    emu.v[0xF] = emu.v[x] & 0x1;
-                    emu.v[x] >>= 1;
+                    let value = if emu.quirks.shift { emu.v[x] } else { emu.v[y] };
+                    emu.v[x] = value >> 1;
+                    emu.v[0xF] = value & 0x1;
                     emu.pc += 2;
                 }
                 0x7 => {
@@ -102,84 +167,115 @@ pub fn execute(emu: &mut Emulator, opcode: u16) {
                     emu.pc += 2;
                 }
                 0xE => {
-                    emu.v[0xF] = (emu.v[x] & 0x80) >> 7;
-                    emu.v[x] <<= 1;
-                    emu.pc += 2;
-                }
-                _ => {
+                    let value = if emu.quirks.shift { emu.v[x] } else { emu.v[y] };
+                    emu.v[x] = value << 1;
+                    emu.v[0xF] = (value & 0x80) >> 7;
                     emu.pc += 2;
                 }
+                _ => return Err(Fault::UnknownOpcode(opcode)),
             }
         }
-        0x9000 => {
-            if emu.v[x] != emu.v[y] {
-                emu.pc += 4;
-            } else {
-                emu.pc += 2;
-            }
+        0x9000 if n == 0 => {
+            skip_if(emu, emu.v[x] != emu.v[y])?;
         }
         0xA000 => {
             emu.i = nnn;
             emu.pc += 2;
         }
         0xB000 => {
-            emu.pc = nnn + emu.v[0] as u16;
+            let offset = if emu.quirks.jump_vx { emu.v[x] } else { emu.v[0] };
+            emu.pc = nnn + offset as u16;
         }
         0xC000 => {
-            let random: u8 = rand::thread_rng().gen();
+            let random = emu.rng.next_u8();
             emu.v[x] = random & nn;
             emu.pc += 2;
         }
         0xD000 => {
-            let x_coord = emu.v[x] as usize % 64;
-            let y_coord = emu.v[y] as usize % 32;
+            let (width, height) = (emu.width(), emu.height());
+            let x_coord = emu.v[x] as usize % width;
+            let y_coord = emu.v[y] as usize % height;
+
+            // DXY0 draws a 16x16 sprite stored as two bytes per row.
+            let (rows, cols) = if n == 0 { (16, 16) } else { (n as usize, 8) };
+            let bytes_per_row = cols / 8;
+
+            // With several planes selected, the sprite data for each plane
+            // follows the previous one in memory.
+            let mut offset = emu.i as usize;
+            let sprite_len = rows * bytes_per_row * emu.planes.count_ones() as usize;
+            emu.check_range(offset, sprite_len)?;
             emu.v[0xF] = 0;
 
-            for row in 0..n as usize {
-                if y_coord + row >= 32 {
-                    break;
+            for plane in 0..4 {
+                let plane_bit = 1u8 << plane;
+                if emu.planes & plane_bit == 0 {
+                    continue;
                 }
 
-                let sprite_byte = emu.memory[emu.i as usize + row];
+                for row in 0..rows {
+                    let address = offset + row * bytes_per_row;
 
-                for col in 0..8 {
-                    if x_coord + col >= 64 {
+                    if emu.quirks.clip && y_coord + row >= height {
                         break;
                     }
 
-                    let pixel = (sprite_byte >> (7 - col)) & 1;
-                    if pixel == 1 {
-                        let idx = (y_coord + row) * 64 + (x_coord + col);
-                        if emu.display[idx] {
-                            emu.v[0xF] = 1; 
+                    let sprite_row = if bytes_per_row == 2 {
+                        (emu.read_byte(address)? as u16) << 8 | emu.read_byte(address + 1)? as u16
+                    } else {
+                        (emu.read_byte(address)? as u16) << 8
+                    };
+
+                    for col in 0..cols {
+                        if emu.quirks.clip && x_coord + col >= width {
+                            break;
+                        }
+
+                        let pixel = (sprite_row >> (15 - col)) & 1;
+                        if pixel == 1 {
+                            let idx = ((y_coord + row) % height) * width + (x_coord + col) % width;
+                            if emu.display[idx] & plane_bit != 0 {
+                                emu.v[0xF] = 1; 
+                            }
+                            emu.display[idx] ^= plane_bit;
                         }
-                        emu.display[idx] ^= true;
                     }
                 }
+
+                offset += rows * bytes_per_row;
             }
 
+            if emu.quirks.display_wait {
+                emu.vblank_wait = true;
+            }
             emu.pc += 2;
         }
         0xE000 => match nn {
             0x9E => {
-                if emu.keypad[emu.v[x] as usize] {
-                    emu.pc += 4;
-                } else {
-                    emu.pc += 2;
-                }
+                skip_if(emu, emu.keypad[emu.v[x] as usize & 0x0F])?;
             }
             0xA1 => {
-                if !emu.keypad[emu.v[x] as usize] {
-                    emu.pc += 4;
-                } else {
-                    emu.pc += 2;
-                }
-            }
-            _ => {
-                emu.pc += 2;
+                skip_if(emu, !emu.keypad[emu.v[x] as usize & 0x0F])?;
             }
+            _ => return Err(Fault::UnknownOpcode(opcode)),
         },
         0xF000 => match nn {
+            0x00 if x == 0 => {
+                emu.i = emu.peek_word(emu.pc as usize + 2)?;
+                emu.pc += 4;
+            }
+            0x01 => {
+                emu.planes = x as u8;
+                emu.pc += 2;
+            }
+            0x02 if x == 0 => {
+                let start = emu.i as usize;
+                emu.check_range(start, 16)?;
+                for offset in 0..16 {
+                    emu.audio_pattern[offset] = emu.read_byte(start + offset)?;
+                }
+                emu.pc += 2;
+            }
             0x07 => {
                 emu.v[x] = emu.delay_timer;
                 emu.pc += 2;
@@ -217,33 +313,387 @@ pub fn execute(emu: &mut Emulator, opcode: u16) {
                 emu.i = (emu.v[x] as u16 & 0x0F) * 5;
                 emu.pc += 2;
             }
+            0x30 => {
+                emu.i = BIG_FONT_ADDR + (emu.v[x] as u16 & 0x0F) * 10;
+                emu.pc += 2;
+            }
             0x33 => {
                 let value = emu.v[x];
-                emu.memory[emu.i as usize] = value / 100;
-                emu.memory[emu.i as usize + 1] = (value / 10) % 10;
-                emu.memory[emu.i as usize + 2] = value % 10;
+                let addr = emu.i as usize;
+                emu.check_range(addr, 3)?;
+                emu.write_byte(addr, value / 100)?;
+                emu.write_byte(addr + 1, (value / 10) % 10)?;
+                emu.write_byte(addr + 2, value % 10)?;
+                emu.pc += 2;
+            }
+            0x3A => {
+                emu.pitch = emu.v[x];
                 emu.pc += 2;
             }
             0x55 => {
+                emu.check_range(emu.i as usize, x + 1)?;
                 for i in 0..=x {
-                    emu.memory[emu.i as usize + i] = emu.v[i];
+                    emu.write_byte(emu.i as usize + i, emu.v[i])?;
                 }
+                advance_i(emu, x);
                 emu.pc += 2;
             }
             0x65 => {
+                emu.check_range(emu.i as usize, x + 1)?;
                 for i in 0..=x {
-                    emu.v[i] = emu.memory[emu.i as usize + i];
+                    emu.v[i] = emu.read_byte(emu.i as usize + i)?;
                 }
+                advance_i(emu, x);
                 emu.pc += 2;
             }
-            _ => {
+            0x75 => {
+                emu.rpl[..=x].copy_from_slice(&emu.v[..=x]);
                 emu.pc += 2;
             }
+            0x85 => {
+                emu.v[..=x].copy_from_slice(&emu.rpl[..=x]);
+                emu.pc += 2;
+            }
+            _ => return Err(Fault::UnknownOpcode(opcode)),
         },
-        _ => {
-            emu.pc += 2;
+        _ => return Err(Fault::UnknownOpcode(opcode)),
+    }
+
+    Ok(())
+}
+
+/// Skips the next instruction when `condition` holds. The XO-CHIP
+/// `F000 NNNN` instruction is four bytes long, so it is skipped whole.
+fn skip_if(emu: &mut Emulator, condition: bool) -> Result<(), Fault> {
+    let mut next = next_pc(emu.pc, 2)?;
+    if condition {
+        let at = next as usize;
+        let is_long = at + 1 < emu.memory.len()
+            && emu.memory[at] == 0xF0
+            && emu.memory[at + 1] == 0x00;
+        next = next_pc(next, if is_long { 4 } else { 2 })?;
+    }
+    emu.pc = next;
+    Ok(())
+}
+
+/// Moves I on after `FX55`/`FX65` as the memory quirks say.
+fn advance_i(emu: &mut Emulator, x: usize) {
+    if emu.quirks.memory_increment {
+        emu.i = emu.i.wrapping_add(x as u16 + 1);
+    } else if emu.quirks.memory_increment_by_x {
+        emu.i = emu.i.wrapping_add(x as u16);
+    }
+}
+
+/// The address `len` bytes after `pc`, or a fault if that is past the end
+/// of the 16-bit address space.
+fn next_pc(pc: u16, len: u16) -> Result<u16, Fault> {
+    pc.checked_add(len)
+        .ok_or(Fault::MemoryOutOfBounds { addr: pc as usize + len as usize })
+}
+
+/// Registers VX through VY inclusive, counting down if X > Y.
+fn register_range(x: usize, y: usize) -> Box<dyn Iterator<Item = usize>> {
+    if x <= y {
+        Box::new(x..=y)
+    } else {
+        Box::new((y..=x).rev())
+    }
+}
+
+/// Shifts the selected planes of the visible framebuffer by `dx` columns and
+/// `dy` rows, filling the vacated area with blank pixels.
+fn scroll(emu: &mut Emulator, dx: isize, dy: isize) {
+    let (width, height) = (emu.width() as isize, emu.height() as isize);
+    let planes = emu.planes;
+    let source = emu.display;
+
+    for y in 0..height {
+        for x in 0..width {
+            let (src_x, src_y) = (x - dx, y - dy);
+            let inside = (0..width).contains(&src_x) && (0..height).contains(&src_y);
+            let moved = if inside { source[(src_y * width + src_x) as usize] } else { 0 };
+            let idx = (y * width + x) as usize;
+            emu.display[idx] = (emu.display[idx] & !planes) | (moved & planes);
         }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::HIRES_WIDTH;
+    use crate::quirks::{Platform, Quirks};
+
+    /// An XO-CHIP emulator with `opcode` at `pc`.
+    fn at(pc: u16, opcode: u16) -> Emulator {
+        let mut emu = Emulator::with_platform(Platform::XoChip);
+        emu.pc = pc;
+        emu.memory[pc as usize..pc as usize + 2].copy_from_slice(&opcode.to_be_bytes());
+        emu
+    }
+
+    #[test]
+    fn running_off_the_end_of_memory_faults() {
+        let mut emu = at(0xFFFE, 0x6001);
+        assert_eq!(emu.cycle(), Err(Fault::MemoryOutOfBounds { addr: 0x10000 }));
+        assert_eq!((emu.pc, emu.v[0]), (0xFFFE, 0));
+    }
+
+    #[test]
+    fn skipping_off_the_end_of_memory_faults() {
+        let mut emu = at(0xFFFC, 0x3000);
+        assert_eq!(emu.cycle(), Err(Fault::MemoryOutOfBounds { addr: 0x10000 }));
+        assert_eq!(emu.pc, 0xFFFC);
+    }
+
+    #[test]
+    fn jumps_from_the_last_word_are_allowed() {
+        let mut emu = at(0xFFFE, 0x1200);
+        assert_eq!(emu.cycle(), Ok(()));
+        assert_eq!(emu.pc, 0x200);
+    }
+
+    #[test]
+    fn sprite_past_the_end_of_memory_leaves_vf_alone() {
+        let mut emu = Emulator::new();
+        emu.memory[0x200..0x202].copy_from_slice(&[0xD0, 0x15]);
+        emu.v[0xF] = 7;
+        emu.i = 0xFFE;
+        assert_eq!(emu.cycle(), Err(Fault::MemoryOutOfBounds { addr: 0x1000 }));
+        assert_eq!((emu.pc, emu.v[0xF]), (0x200, 7));
+    }
+
+    #[test]
+    fn returning_past_the_end_of_memory_faults() {
+        let mut emu = at(0x300, 0x00EE);
+        emu.stack[0] = 0xFFFE;
+        emu.sp = 1;
+        assert_eq!(emu.cycle(), Err(Fault::MemoryOutOfBounds { addr: 0x10000 }));
+        assert_eq!((emu.pc, emu.sp), (0x300, 1));
+    }
+
+    #[test]
+    fn extensions_run_on_every_platform() {
+        let mut emu = Emulator::with_platform(Platform::Chip8);
+        emu.load_rom(&[0xF0, 0x00, 0x03, 0x00, 0xF2, 0x01, 0x00, 0xFF]);
+        for _ in 0..3 {
+            emu.cycle().unwrap();
+        }
+        assert_eq!((emu.i, emu.planes, emu.pc), (0x300, 2, 0x208));
+        assert_eq!(emu.width(), 128);
+    }
+
+    #[test]
+    fn memory_quirks_move_i() {
+        for (increment, by_x, i) in [(true, false, 0x304), (false, true, 0x303), (false, false, 0x300)] {
+            let mut emu = Emulator::new();
+            emu.quirks.memory_increment = increment;
+            emu.quirks.memory_increment_by_x = by_x;
+            emu.load_rom(&[0xF3, 0x55, 0xF3, 0x65]);
+            for _ in 0..2 {
+                emu.i = 0x300;
+                emu.cycle().unwrap();
+            }
+            assert_eq!(emu.i, i);
+        }
+    }
+
+    /// Runs `program` to the end under `quirks`.
+    fn run_with(quirks: Quirks, program: &[u8]) -> Emulator {
+        let mut emu = Emulator::new();
+        emu.quirks = quirks;
+        emu.load_rom(program);
+        for _ in 0..program.len() / 2 {
+            emu.cycle().unwrap();
+        }
+        emu
+    }
+
+    #[test]
+    fn shift_quirk_shifts_vx_in_place() {
+        // V0 = 0x81, V1 = 0x02; SHR V0, V1; SHL V2, V1 with V2 = 0x81
+        let program = [0x60, 0x81, 0x61, 0x02, 0x62, 0x81, 0x80, 0x16, 0x82, 0x1E];
+        let emu = run_with(Quirks { shift: true, ..Quirks::default() }, &program);
+        assert_eq!((emu.v[0], emu.v[2], emu.v[0xF]), (0x40, 0x02, 1));
+        let emu = run_with(Quirks { shift: false, ..Quirks::default() }, &program);
+        assert_eq!((emu.v[0], emu.v[2], emu.v[0xF]), (0x01, 0x04, 0));
+    }
+
+    #[test]
+    fn jump_quirk_adds_vx() {
+        // V0 = 0x10, V3 = 0x20; JP V0, 0x300
+        let program = [0x60, 0x10, 0x63, 0x20, 0xB3, 0x00];
+        let emu = run_with(Quirks { jump_vx: true, ..Quirks::default() }, &program);
+        assert_eq!(emu.pc, 0x320);
+        let emu = run_with(Quirks { jump_vx: false, ..Quirks::default() }, &program);
+        assert_eq!(emu.pc, 0x310);
+    }
+
+    #[test]
+    fn vf_reset_quirk_clears_vf_after_logic() {
+        for op in [0x01, 0x02, 0x03] {
+            let program = [0x6F, 0x07, 0x80, 0x10 | op];
+            let emu = run_with(Quirks { vf_reset: true, ..Quirks::default() }, &program);
+            assert_eq!(emu.v[0xF], 0);
+            let emu = run_with(Quirks { vf_reset: false, ..Quirks::default() }, &program);
+            assert_eq!(emu.v[0xF], 7);
+        }
+    }
+
+    #[test]
+    fn clip_quirk_stops_sprites_at_the_edge() {
+        // Draw a full 8x2 block at (60, 31) from 0x208.
+        let program = [0x60, 0x3C, 0x61, 0x1F, 0xA2, 0x0A, 0xD0, 0x12, 0x00, 0x00, 0xFF, 0xFF];
+        let mut emu = Emulator::new();
+        emu.quirks = Quirks { clip: true, ..Quirks::default() };
+        emu.load_rom(&program);
+        for _ in 0..4 {
+            emu.cycle().unwrap();
+        }
+        let lit = |emu: &Emulator, x: usize, y: usize| emu.display[y * emu.width() + x] != 0;
+        assert!(lit(&emu, 63, 31) && !lit(&emu, 0, 31) && !lit(&emu, 60, 0));
+
+        emu.reset();
+        emu.quirks = Quirks { clip: false, ..Quirks::default() };
+        for _ in 0..4 {
+            emu.cycle().unwrap();
+        }
+        assert!(lit(&emu, 63, 31) && lit(&emu, 3, 31) && lit(&emu, 60, 0) && lit(&emu, 3, 0));
+    }
+
+    #[test]
+    fn display_wait_quirk_ends_the_frame_after_drawing() {
+        // DRW V0, V0, 1; JP 0x200
+        let program = [0xD0, 0x01, 0x12, 0x00];
+        let mut emu = Emulator::new();
+        emu.cycles_per_frame = 10;
+        emu.load_rom(&program);
+        emu.quirks.display_wait = true;
+        emu.run_frame([false; 16]).unwrap();
+        assert_eq!(emu.instruction_count, 1);
+
+        emu.quirks.display_wait = false;
+        emu.run_frame([false; 16]).unwrap();
+        assert_eq!(emu.instruction_count, 11);
+    }
+
+    /// A SUPER-CHIP emulator in hi-res mode with `program` loaded.
+    fn hires(program: &[u8]) -> Emulator {
+        let mut emu = Emulator::with_platform(Platform::SuperChip);
+        emu.load_rom(program);
+        emu.set_hires(true);
+        emu
+    }
+
+    /// Like [`hires`], with one pixel lit at (`x`, `y`).
+    fn hires_with_pixel(x: usize, y: usize, program: &[u8]) -> Emulator {
+        let mut emu = hires(program);
+        emu.display[y * HIRES_WIDTH + x] = 1;
+        emu
+    }
+
+    fn lit(emu: &Emulator) -> Vec<(usize, usize)> {
+        let width = emu.width();
+        (0..width * emu.height())
+            .filter(|&index| emu.display[index] != 0)
+            .map(|index| (index % width, index / width))
+            .collect()
+    }
+
+    #[test]
+    fn scrolling_moves_the_display() {
+        for (opcode, moved) in [
+            (0x00C3, vec![(10, 23)]),
+            (0x00D3, vec![(10, 17)]),
+            (0x00FB, vec![(14, 20)]),
+            (0x00FC, vec![(6, 20)]),
+        ] {
+            let mut emu = hires_with_pixel(10, 20, &(opcode as u16).to_be_bytes());
+            emu.cycle().unwrap();
+            assert_eq!(lit(&emu), moved, "{:04X}", opcode);
+        }
+    }
 
+    #[test]
+    fn scrolling_drops_pixels_off_the_edge() {
+        for (opcode, x, y) in [(0x00CF, 0, 60), (0x00DF, 0, 3), (0x00FB, 125, 0), (0x00FC, 2, 0)] {
+            let mut emu = hires_with_pixel(x, y, &(opcode as u16).to_be_bytes());
+            emu.cycle().unwrap();
+            assert_eq!(lit(&emu), [], "{:04X}", opcode);
+        }
+    }
+
+    #[test]
+    fn resolution_switches_clear_the_display() {
+        let mut emu = hires_with_pixel(100, 50, &[0x00, 0xFE, 0x00, 0xFF]);
+        assert_eq!((emu.width(), emu.height()), (128, 64));
+        emu.cycle().unwrap();
+        assert_eq!((emu.width(), emu.height()), (64, 32));
+        assert_eq!(lit(&emu), []);
+
+        emu.display[0] = 1;
+        emu.cycle().unwrap();
+        assert_eq!((emu.width(), emu.height()), (128, 64));
+        assert_eq!(lit(&emu), []);
+    }
+
+    #[test]
+    fn dxy0_draws_a_16x16_sprite_in_hires() {
+        // LD V0, 120; LD V1, 60; LD I, 0x20A; DRW V0, V1, 0; DRW V0, V1, 0,
+        // then a sprite with only its top left and bottom right pixels set.
+        let mut program = vec![0x60, 0x78, 0x61, 0x3C, 0xA2, 0x0A, 0xD0, 0x10, 0xD0, 0x10];
+        let mut sprite = [0u8; 32];
+        sprite[0] = 0x80;
+        sprite[31] = 0x01;
+        program.extend_from_slice(&sprite);
+
+        let mut emu = hires(&program);
+        for _ in 0..4 {
+            emu.cycle().unwrap();
+        }
+        // The sprite is clipped at the bottom right corner.
+        assert_eq!(lit(&emu), [(120, 60)]);
+        assert_eq!(emu.v[0xF], 0);
+
+        // Drawing again so the bottom right corner lands on that pixel
+        // erases it and reports the collision.
+        emu.v[0] = 105;
+        emu.v[1] = 45;
+        emu.cycle().unwrap();
+        assert_eq!(lit(&emu), [(105, 45)]);
+        assert_eq!(emu.v[0xF], 1);
+    }
+
+    #[test]
+    fn big_font_digits_are_ten_bytes_apart() {
+        let mut emu = at(0x200, 0xF330);
+        emu.v[3] = 0x17;
+        emu.cycle().unwrap();
+        assert_eq!(emu.i, BIG_FONT_ADDR + 7 * 10);
+    }
+
+    #[test]
+    fn flag_registers_round_trip() {
+        // FX75 saves V0-V3, then FX85 restores V0-V1 only.
+        let mut emu = at(0x200, 0xF375);
+        emu.memory[0x202..0x204].copy_from_slice(&[0xF1, 0x85]);
+        emu.v[..4].copy_from_slice(&[1, 2, 3, 4]);
+        emu.cycle().unwrap();
+        assert_eq!(emu.rpl[..5], [1, 2, 3, 4, 0]);
+
+        emu.v[..4].copy_from_slice(&[9, 9, 9, 9]);
+        emu.cycle().unwrap();
+        assert_eq!(emu.v[..4], [1, 2, 9, 9]);
+    }
+
+    #[test]
+    fn exit_stops_execution() {
+        let mut emu = at(0x200, 0x00FD);
+        emu.cycle().unwrap();
+        assert!(emu.exited);
+        emu.cycle().unwrap();
+        assert_eq!((emu.pc, emu.instruction_count), (0x200, 1));
+    }
+}
@@ -0,0 +1,206 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Behaviours that differ between CHIP-8 interpreters. ROMs written for one
+/// interpreter often break on another, so these are configurable per run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quirks {
+    /// `8XY6`/`8XYE` shift VX in place and ignore VY.
+    pub shift: bool,
+
+    /// `FX55`/`FX65` leave I pointing past the last register stored/loaded.
+    pub memory_increment: bool,
+
+    /// Without `memory_increment`, `FX55`/`FX65` leave I pointing at the last
+    /// register stored/loaded instead of leaving it alone, as CHIP-48 does.
+    pub memory_increment_by_x: bool,
+
+    /// `BNNN` jumps to `NNN + VX` (X being the high nibble of NNN) instead of `NNN + V0`.
+    pub jump_vx: bool,
+
+    /// `8XY1`/`8XY2`/`8XY3` reset VF to zero.
+    pub vf_reset: bool,
+
+    /// `DXYN` clips sprites at the screen edge instead of wrapping them around.
+    pub clip: bool,
+
+    /// `DXYN` waits for the next frame before execution continues.
+    pub display_wait: bool,
+}
+
+impl Default for Quirks {
+    /// The behaviour term-8 has always had, which is what most classic
+    /// CHIP-8 ROMs floating around the internet expect.
+    fn default() -> Self {
+        Self {
+            shift: true,
+            memory_increment: false,
+            memory_increment_by_x: false,
+            jump_vx: false,
+            vf_reset: false,
+            clip: true,
+            display_wait: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Platform {
+    /// The original COSMAC VIP interpreter.
+    Chip8,
+    /// CHIP-8 as most modern interpreters run it, without any quirks.
+    ModernChip8,
+    /// CHIP-48 on the HP-48 calculators.
+    Chip48,
+    /// SUPER-CHIP 1.1.
+    SuperChip,
+    /// Octo's XO-CHIP extension.
+    XoChip,
+}
+
+impl Platform {
+    pub const ALL: [Platform; 5] = [
+        Platform::Chip8,
+        Platform::ModernChip8,
+        Platform::Chip48,
+        Platform::SuperChip,
+        Platform::XoChip,
+    ];
+
+    pub fn quirks(self) -> Quirks {
+        match self {
+            Platform::Chip8 => Quirks {
+                shift: false,
+                memory_increment: true,
+                memory_increment_by_x: false,
+                jump_vx: false,
+                vf_reset: true,
+                clip: true,
+                display_wait: true,
+            },
+            Platform::ModernChip8 => Quirks {
+                shift: false,
+                memory_increment: true,
+                memory_increment_by_x: false,
+                jump_vx: false,
+                vf_reset: false,
+                clip: true,
+                display_wait: false,
+            },
+            Platform::Chip48 | Platform::SuperChip => Quirks {
+                shift: true,
+                memory_increment: false,
+                memory_increment_by_x: false,
+                jump_vx: true,
+                vf_reset: false,
+                clip: true,
+                display_wait: false,
+            },
+            Platform::XoChip => Quirks {
+                shift: false,
+                memory_increment: true,
+                memory_increment_by_x: false,
+                jump_vx: false,
+                vf_reset: false,
+                clip: false,
+                display_wait: false,
+            },
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Platform::Chip8 => "chip8",
+            Platform::ModernChip8 => "modern",
+            Platform::Chip48 => "chip48",
+            Platform::SuperChip => "schip",
+            Platform::XoChip => "xochip",
+        }
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Platform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace(['-', '_'], "").as_str() {
+            "chip8" | "vip" => Ok(Platform::Chip8),
+            "modern" | "modernchip8" => Ok(Platform::ModernChip8),
+            "chip48" => Ok(Platform::Chip48),
+            "schip" | "superchip" => Ok(Platform::SuperChip),
+            "xochip" => Ok(Platform::XoChip),
+            _ => {
+                let names: Vec<&str> = Platform::ALL.iter().map(|p| p.name()).collect();
+                Err(format!("unknown platform '{}' (expected one of: {})", s, names.join(", ")))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn platforms_parse_from_their_names() {
+        for platform in Platform::ALL {
+            assert_eq!(platform.name().parse(), Ok(platform));
+            assert_eq!(platform.to_string(), platform.name());
+        }
+        assert_eq!("VIP".parse(), Ok(Platform::Chip8));
+        assert_eq!("Modern-CHIP8".parse(), Ok(Platform::ModernChip8));
+        assert_eq!("super_chip".parse(), Ok(Platform::SuperChip));
+        assert_eq!("XO-CHIP".parse(), Ok(Platform::XoChip));
+    }
+
+    #[test]
+    fn unknown_platforms_list_the_known_ones() {
+        assert_eq!(
+            "chip10".parse::<Platform>(),
+            Err("unknown platform 'chip10' (expected one of: chip8, modern, chip48, schip, xochip)".to_string())
+        );
+    }
+
+    #[test]
+    fn presets() {
+        let chip8 = Platform::Chip8.quirks();
+        assert!(chip8.vf_reset && chip8.display_wait && chip8.memory_increment && chip8.clip);
+        assert!(!chip8.shift && !chip8.jump_vx);
+
+        // Modern CHIP-8 is the VIP without its timing-related quirks.
+        let modern = Platform::ModernChip8.quirks();
+        assert_eq!(
+            modern,
+            Quirks {
+                vf_reset: false,
+                display_wait: false,
+                ..chip8
+            }
+        );
+
+        let schip = Platform::SuperChip.quirks();
+        assert_eq!(Platform::Chip48.quirks(), schip);
+        assert!(schip.shift && schip.jump_vx && schip.clip);
+        assert!(!schip.memory_increment && !schip.vf_reset && !schip.display_wait);
+
+        let xochip = Platform::XoChip.quirks();
+        assert_eq!(xochip, Quirks { clip: false, ..modern });
+
+        for platform in Platform::ALL {
+            assert!(!platform.quirks().memory_increment_by_x);
+        }
+    }
+
+    #[test]
+    fn default_is_not_a_preset() {
+        for platform in Platform::ALL {
+            assert_ne!(platform.quirks(), Quirks::default());
+        }
+    }
+}
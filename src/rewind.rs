@@ -0,0 +1,245 @@
+use std::collections::VecDeque;
+
+/// Ring buffer of recent emulator snapshots (as produced by
+/// `savestate::encode`) for stepping backwards in time.
+///
+/// Only the newest snapshot is kept whole. Every older one is stored as a
+/// compressed delta against the snapshot after it, so a frame where little
+/// changed costs a few bytes. Stepping back applies the newest delta; the
+/// oldest deltas are dropped once the frame or byte budget is exceeded.
+pub struct Rewind {
+    newest: Option<Vec<u8>>,
+    deltas: VecDeque<Vec<u8>>,
+    delta_bytes: usize,
+    max_frames: usize,
+    max_bytes: usize,
+}
+
+const DELTA_XOR: u8 = 0;
+const DELTA_FULL: u8 = 1;
+
+impl Rewind {
+    pub fn new(max_frames: usize, max_bytes: usize) -> Self {
+        Self {
+            newest: None,
+            deltas: VecDeque::new(),
+            delta_bytes: 0,
+            max_frames,
+            max_bytes,
+        }
+    }
+
+    /// Number of frames that can currently be stepped back.
+    pub fn len(&self) -> usize {
+        self.deltas.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deltas.is_empty()
+    }
+
+    /// Approximate memory held by the buffer.
+    pub fn memory_usage(&self) -> usize {
+        self.delta_bytes + self.newest.as_ref().map_or(0, Vec::len)
+    }
+
+    pub fn clear(&mut self) {
+        self.newest = None;
+        self.deltas.clear();
+        self.delta_bytes = 0;
+    }
+
+    pub fn push(&mut self, snapshot: Vec<u8>) {
+        if let Some(previous) = self.newest.take() {
+            let delta = encode_delta(&snapshot, &previous);
+            self.delta_bytes += delta.len();
+            self.deltas.push_back(delta);
+        }
+        self.newest = Some(snapshot);
+
+        while self.deltas.len() > self.max_frames || self.memory_usage() > self.max_bytes {
+            match self.deltas.pop_front() {
+                Some(dropped) => self.delta_bytes -= dropped.len(),
+                None => break,
+            }
+        }
+    }
+
+    /// Discards the newest snapshot and returns the one before it, or `None`
+    /// when there is no older frame left.
+    pub fn step_back(&mut self) -> Option<&[u8]> {
+        let delta = self.deltas.pop_back()?;
+        self.delta_bytes -= delta.len();
+
+        let current = self.newest.take()?;
+        self.newest = Some(apply_delta(&current, &delta));
+        self.newest.as_deref()
+    }
+}
+
+/// Encodes `target` relative to `base`: the XOR of the two, with runs of
+/// unchanged (zero) bytes collapsed. Snapshots of different lengths fall back
+/// to storing `target` whole.
+fn encode_delta(base: &[u8], target: &[u8]) -> Vec<u8> {
+    if base.len() != target.len() {
+        let mut out = Vec::with_capacity(target.len() + 1);
+        out.push(DELTA_FULL);
+        out.extend_from_slice(target);
+        return out;
+    }
+
+    let mut out = vec![DELTA_XOR];
+    let mut pos = 0;
+
+    while pos < base.len() {
+        let unchanged = base[pos..]
+            .iter()
+            .zip(&target[pos..])
+            .take_while(|(a, b)| a == b)
+            .count();
+        pos += unchanged;
+
+        let changed = base[pos..]
+            .iter()
+            .zip(&target[pos..])
+            .take_while(|(a, b)| a != b)
+            .count();
+
+        write_varint(&mut out, unchanged);
+        write_varint(&mut out, changed);
+        out.extend(base[pos..pos + changed].iter().zip(&target[pos..pos + changed]).map(|(a, b)| a ^ b));
+        pos += changed;
+    }
+
+    out
+}
+
+fn apply_delta(base: &[u8], delta: &[u8]) -> Vec<u8> {
+    if delta.first() == Some(&DELTA_FULL) {
+        return delta[1..].to_vec();
+    }
+
+    let mut out = base.to_vec();
+    let mut pos = 0;
+    let mut cursor = 1;
+
+    while cursor < delta.len() {
+        pos += read_varint(delta, &mut cursor);
+        let changed = read_varint(delta, &mut cursor);
+        for (byte, mask) in out[pos..pos + changed].iter_mut().zip(&delta[cursor..cursor + changed]) {
+            *byte ^= mask;
+        }
+        cursor += changed;
+        pos += changed;
+    }
+
+    out
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(data: &[u8], cursor: &mut usize) -> usize {
+    let mut value = 0;
+    let mut shift = 0;
+    while let Some(&byte) = data.get(*cursor) {
+        *cursor += 1;
+        value |= ((byte & 0x7F) as usize) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::Emulator;
+    use crate::savestate;
+
+    /// Snapshots with small edits, long unchanged runs (multi-byte varints),
+    /// a fully changed one and a change of length.
+    fn snapshots() -> Vec<Vec<u8>> {
+        let mut states = vec![vec![0u8; 5000]];
+        let mut next = |edit: &dyn Fn(&mut Vec<u8>)| {
+            let mut state = states.last().unwrap().clone();
+            edit(&mut state);
+            states.push(state);
+        };
+        next(&|s| s[0] = 1);
+        next(&|s| s[4999] = 2);
+        next(&|s| s[200..400].fill(3));
+        next(&|s| s.iter_mut().for_each(|b| *b = b.wrapping_add(1)));
+        next(&|s| s.truncate(100));
+        next(&|s| s.extend_from_slice(&[9; 300]));
+        next(&|_| {});
+        states
+    }
+
+    #[test]
+    fn stepping_back_restores_each_snapshot() {
+        let states = snapshots();
+        let mut rewind = Rewind::new(100, usize::MAX);
+        for state in &states {
+            rewind.push(state.clone());
+        }
+        assert_eq!(rewind.len(), states.len() - 1);
+
+        for expected in states.iter().rev().skip(1) {
+            assert_eq!(rewind.step_back(), Some(expected.as_slice()));
+        }
+        assert_eq!(rewind.step_back(), None);
+        assert!(rewind.is_empty());
+    }
+
+    #[test]
+    fn emulator_frames_rewind_exactly() {
+        let mut emu = Emulator::new();
+        emu.set_seed(7);
+        emu.load_rom(include_bytes!("../roms/pong.ch8"));
+        let mut rewind = Rewind::new(100, usize::MAX);
+        let mut states = Vec::new();
+        for _ in 0..30 {
+            emu.run_frame([false; 16]).unwrap();
+            let state = savestate::encode(&emu);
+            rewind.push(state.clone());
+            states.push(state);
+        }
+
+        for expected in states.iter().rev().skip(1) {
+            assert_eq!(rewind.step_back(), Some(expected.as_slice()));
+        }
+    }
+
+    #[test]
+    fn oldest_frames_are_dropped_first() {
+        let states = snapshots();
+        let mut rewind = Rewind::new(3, usize::MAX);
+        for state in &states {
+            rewind.push(state.clone());
+        }
+        assert_eq!(rewind.len(), 3);
+        for expected in states.iter().rev().skip(1).take(3) {
+            assert_eq!(rewind.step_back(), Some(expected.as_slice()));
+        }
+        assert_eq!(rewind.step_back(), None);
+    }
+
+    #[test]
+    fn varints_round_trip() {
+        for value in [0, 1, 0x7F, 0x80, 0x3FFF, 0x4000, 1 << 20, usize::MAX >> 1] {
+            let mut out = Vec::new();
+            write_varint(&mut out, value);
+            let mut cursor = 0;
+            assert_eq!(read_varint(&out, &mut cursor), value);
+            assert_eq!(cursor, out.len());
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Deterministic xorshift64* generator behind `CXNN`. Its whole state is a
+/// single word, so it is cloned along with checkpoints and a run can be
+/// reproduced from its seed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // xorshift gets stuck on zero, so scramble the seed first (splitmix64).
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+
+        Self {
+            state: if z == 0 { 1 } else { z },
+        }
+    }
+
+    pub fn from_state(state: u64) -> Self {
+        Self {
+            state: if state == 0 { 1 } else { state },
+        }
+    }
+
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn next_u8(&mut self) -> u8 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        (self.state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 56) as u8
+    }
+}
+
+/// A seed taken from the clock, for runs where no `--seed` was given.
+pub fn random_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sequence(rng: &mut Rng) -> Vec<u8> {
+        (0..64).map(|_| rng.next_u8()).collect()
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_numbers() {
+        assert_eq!(sequence(&mut Rng::new(1234)), sequence(&mut Rng::new(1234)));
+        assert_ne!(sequence(&mut Rng::new(1234)), sequence(&mut Rng::new(1235)));
+    }
+
+    #[test]
+    fn restoring_the_state_continues_the_sequence() {
+        let mut rng = Rng::new(99);
+        sequence(&mut rng);
+        let mut restored = Rng::from_state(rng.state());
+        assert_eq!(sequence(&mut restored), sequence(&mut rng));
+    }
+
+    #[test]
+    fn zero_does_not_get_stuck() {
+        for mut rng in [Rng::new(0), Rng::from_state(0)] {
+            let numbers = sequence(&mut rng);
+            assert!(numbers.iter().any(|&n| n != numbers[0]));
+        }
+    }
+
+    #[test]
+    fn numbers_cover_the_whole_byte() {
+        let mut rng = Rng::new(7);
+        let mut seen = [false; 256];
+        for _ in 0..4096 {
+            seen[rng.next_u8() as usize] = true;
+        }
+        assert!(seen.iter().all(|&seen| seen));
+    }
+}
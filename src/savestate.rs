@@ -0,0 +1,343 @@
+use crate::emulator::{Emulator, HIRES_HEIGHT, HIRES_WIDTH, MEMORY_SIZE, XO_MEMORY_SIZE};
+use crate::quirks::Quirks;
+use crate::rng::Rng;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 4] = b"T8ST";
+
+/// Bumped whenever the layout below changes; older files are rejected
+/// rather than misread.
+pub const FORMAT_VERSION: u16 = 1;
+
+pub const SLOT_COUNT: u8 = 9;
+
+#[derive(Debug)]
+pub enum SaveStateError {
+    Io(io::Error),
+    NotASaveState,
+    UnsupportedVersion(u16),
+    RomMismatch { expected: String, found: String },
+    Truncated,
+}
+
+impl fmt::Display for SaveStateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveStateError::Io(err) => write!(f, "{}", err),
+            SaveStateError::NotASaveState => write!(f, "not a term-8 save state"),
+            SaveStateError::UnsupportedVersion(version) => write!(
+                f,
+                "save state format v{} is not supported (expected v{})",
+                version, FORMAT_VERSION
+            ),
+            SaveStateError::RomMismatch { expected, found } => write!(
+                f,
+                "save state belongs to a different ROM (sha1 {}, loaded ROM is {})",
+                &found[..8.min(found.len())],
+                &expected[..8.min(expected.len())]
+            ),
+            SaveStateError::Truncated => write!(f, "save state is truncated or corrupt"),
+        }
+    }
+}
+
+impl Error for SaveStateError {}
+
+impl From<io::Error> for SaveStateError {
+    fn from(err: io::Error) -> Self {
+        SaveStateError::Io(err)
+    }
+}
+
+/// Where slot `slot` of the ROM at `rom_path` is stored: next to the ROM, as
+/// `<rom file>.<slot>.state`.
+pub fn slot_path(rom_path: &Path, slot: u8) -> PathBuf {
+    let mut name = rom_path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.state", slot));
+    rom_path.with_file_name(name)
+}
+
+pub fn save(emu: &Emulator, path: &Path) -> Result<(), SaveStateError> {
+    fs::write(path, encode(emu))?;
+    Ok(())
+}
+
+pub fn load(emu: &mut Emulator, path: &Path) -> Result<(), SaveStateError> {
+    let data = fs::read(path)?;
+    decode(emu, &data)
+}
+
+/// Serialises everything needed to resume execution exactly: memory, CPU
+/// registers, stack, timers, display, RNG state and quirks. UI-only state
+/// such as the ghosting buffer and the pause flag is left out.
+pub fn encode(emu: &Emulator) -> Vec<u8> {
+    let mut out = Vec::with_capacity(emu.memory.len() + HIRES_WIDTH * HIRES_HEIGHT + 256);
+
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    out.extend_from_slice(emu.rom_sha1().as_bytes());
+
+    out.extend_from_slice(&(emu.memory.len() as u32).to_le_bytes());
+    out.extend_from_slice(&emu.memory);
+    out.extend_from_slice(&emu.v);
+    out.extend_from_slice(&emu.i.to_le_bytes());
+    out.extend_from_slice(&emu.pc.to_le_bytes());
+    for entry in emu.stack {
+        out.extend_from_slice(&entry.to_le_bytes());
+    }
+    out.push(emu.sp);
+    out.push(emu.delay_timer);
+    out.push(emu.sound_timer);
+    out.push(emu.waiting_for_key.unwrap_or(0xFF));
+
+    out.extend_from_slice(&emu.display);
+    out.push(emu.planes);
+    out.push(emu.hires as u8);
+    out.push(emu.exited as u8);
+    out.extend_from_slice(&emu.rpl);
+    out.extend_from_slice(&emu.audio_pattern);
+    out.push(emu.pitch);
+
+    out.extend_from_slice(&emu.seed.to_le_bytes());
+    out.extend_from_slice(&emu.rng.state().to_le_bytes());
+    out.push(encode_quirks(&emu.quirks));
+
+    out
+}
+
+/// Restores a state produced by [`encode`] into `emu`, which must already
+/// have the same ROM loaded. Nothing is modified if the state is rejected.
+pub fn decode(emu: &mut Emulator, data: &[u8]) -> Result<(), SaveStateError> {
+    let mut reader = Reader { data, pos: 0 };
+
+    if reader.bytes(MAGIC.len())? != MAGIC {
+        return Err(SaveStateError::NotASaveState);
+    }
+    let version = reader.u16()?;
+    if version != FORMAT_VERSION {
+        return Err(SaveStateError::UnsupportedVersion(version));
+    }
+    let found = String::from_utf8_lossy(reader.bytes(40)?).into_owned();
+    let expected = emu.rom_sha1();
+    if found != expected {
+        return Err(SaveStateError::RomMismatch { expected, found });
+    }
+
+    // The history describes how the current state was reached, so it is
+    // neither copied nor kept.
+    let history = std::mem::take(&mut emu.history);
+    let mut state = emu.clone();
+    emu.history = history;
+    state.checkpoint = None;
+
+    // Anything but the two real sizes would break loading a ROM later.
+    let memory_len = reader.u32()? as usize;
+    if memory_len != MEMORY_SIZE && memory_len != XO_MEMORY_SIZE {
+        return Err(SaveStateError::Truncated);
+    }
+    state.memory = reader.bytes(memory_len)?.to_vec();
+    state.v.copy_from_slice(reader.bytes(16)?);
+    state.i = reader.u16()?;
+    state.pc = reader.u16()?;
+    for entry in state.stack.iter_mut() {
+        *entry = reader.u16()?;
+    }
+    state.sp = reader.u8()?;
+    if state.sp as usize > state.stack.len() {
+        return Err(SaveStateError::Truncated);
+    }
+    state.delay_timer = reader.u8()?;
+    state.sound_timer = reader.u8()?;
+    state.waiting_for_key = match reader.u8()? {
+        0xFF => None,
+        register @ 0..=0xF => Some(register),
+        _ => return Err(SaveStateError::Truncated),
+    };
+
+    state.display.copy_from_slice(reader.bytes(HIRES_WIDTH * HIRES_HEIGHT)?);
+    state.planes = reader.u8()?;
+    state.hires = reader.u8()? != 0;
+    state.exited = reader.u8()? != 0;
+    state.rpl.copy_from_slice(reader.bytes(16)?);
+    state.audio_pattern.copy_from_slice(reader.bytes(16)?);
+    state.pitch = reader.u8()?;
+
+    state.seed = reader.u64()?;
+    state.rng = Rng::from_state(reader.u64()?);
+    state.quirks = decode_quirks(reader.u8()?);
+
+    state.ghost_display = [0; HIRES_WIDTH * HIRES_HEIGHT];
+    state.checkpoint = emu.checkpoint.take();
+    state.history = std::mem::take(&mut emu.history);
+    state.history.clear();
+    *emu = state;
+
+    Ok(())
+}
+
+fn encode_quirks(quirks: &Quirks) -> u8 {
+    [
+        quirks.shift,
+        quirks.memory_increment,
+        quirks.jump_vx,
+        quirks.vf_reset,
+        quirks.clip,
+        quirks.display_wait,
+        quirks.memory_increment_by_x,
+    ]
+    .iter()
+    .enumerate()
+    .fold(0, |bits, (bit, &set)| bits | (set as u8) << bit)
+}
+
+fn decode_quirks(bits: u8) -> Quirks {
+    let bit = |n: u8| bits & (1 << n) != 0;
+    Quirks {
+        shift: bit(0),
+        memory_increment: bit(1),
+        jump_vx: bit(2),
+        vf_reset: bit(3),
+        clip: bit(4),
+        display_wait: bit(5),
+        memory_increment_by_x: bit(6),
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], SaveStateError> {
+        let slice = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or(SaveStateError::Truncated)?;
+        self.pos += len;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, SaveStateError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, SaveStateError> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, SaveStateError> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, SaveStateError> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quirks::Platform;
+
+    const ROM: [u8; 6] = [0x60, 0x05, 0x71, 0x01, 0x12, 0x02];
+
+    fn emulator() -> Emulator {
+        let mut emu = Emulator::with_platform(Platform::Chip8);
+        emu.set_seed(42);
+        emu.load_rom(&ROM);
+        emu
+    }
+
+    /// Offset of the memory length, after the magic, version and ROM hash.
+    const MEMORY_LEN_AT: usize = 4 + 2 + 40;
+
+    #[test]
+    fn round_trip_restores_everything() {
+        let mut emu = emulator();
+        for _ in 0..7 {
+            emu.cycle().unwrap();
+        }
+        emu.i = 0x123;
+        emu.delay_timer = 30;
+        emu.display[5] = 1;
+        emu.rng.next_u8();
+        let state = encode(&emu);
+
+        let mut restored = emulator();
+        decode(&mut restored, &state).unwrap();
+        assert_eq!(encode(&restored), state);
+        assert_eq!((restored.pc, restored.v[0], restored.v[1]), (emu.pc, emu.v[0], emu.v[1]));
+        assert_eq!(restored.quirks, emu.quirks);
+        assert_eq!(restored.rng.next_u8(), emu.rng.next_u8());
+    }
+
+    #[test]
+    fn xo_chip_memory_round_trips() {
+        let mut emu = Emulator::with_platform(Platform::XoChip);
+        emu.load_rom(&ROM);
+        emu.memory[0xFFFF] = 0xAB;
+        let state = encode(&emu);
+
+        let mut restored = Emulator::with_platform(Platform::XoChip);
+        restored.load_rom(&ROM);
+        decode(&mut restored, &state).unwrap();
+        assert_eq!(restored.memory.len(), XO_MEMORY_SIZE);
+        assert_eq!(restored.memory[0xFFFF], 0xAB);
+    }
+
+    #[test]
+    fn other_roms_are_rejected() {
+        let state = encode(&emulator());
+        let mut other = Emulator::new();
+        other.load_rom(&[0x00, 0xE0]);
+        let before = encode(&other);
+
+        let err = decode(&mut other, &state).unwrap_err();
+        assert!(matches!(err, SaveStateError::RomMismatch { .. }), "{:?}", err);
+        assert_eq!(encode(&other), before);
+    }
+
+    #[test]
+    fn other_versions_are_rejected() {
+        let mut state = encode(&emulator());
+        state[4..6].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        let err = decode(&mut emulator(), &state).unwrap_err();
+        assert!(matches!(err, SaveStateError::UnsupportedVersion(v) if v == FORMAT_VERSION + 1), "{:?}", err);
+    }
+
+    #[test]
+    fn other_files_are_rejected() {
+        let err = decode(&mut emulator(), b"not a save state").unwrap_err();
+        assert!(matches!(err, SaveStateError::NotASaveState), "{:?}", err);
+    }
+
+    #[test]
+    fn truncated_states_are_rejected_without_changes() {
+        let mut source = emulator();
+        source.cycle().unwrap();
+        let state = encode(&source);
+
+        let mut emu = emulator();
+        let before = encode(&emu);
+        for len in MAGIC.len()..state.len() {
+            let err = decode(&mut emu, &state[..len]).unwrap_err();
+            assert!(matches!(err, SaveStateError::Truncated), "{} bytes: {:?}", len, err);
+        }
+        assert_eq!(encode(&emu), before);
+    }
+
+    #[test]
+    fn odd_memory_sizes_are_rejected() {
+        let mut state = encode(&emulator());
+        state[MEMORY_LEN_AT..MEMORY_LEN_AT + 4].copy_from_slice(&16u32.to_le_bytes());
+        let mut emu = emulator();
+        let err = decode(&mut emu, &state).unwrap_err();
+        assert!(matches!(err, SaveStateError::Truncated), "{:?}", err);
+        assert_eq!(emu.memory.len(), MEMORY_SIZE);
+    }
+}
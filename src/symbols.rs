@@ -0,0 +1,222 @@
+//! Symbol files, mapping ROM addresses to label names.
+//!
+//! Two line formats are accepted and can be mixed:
+//!
+//! ```text
+//! :main 0x200        Octo style, ": main 200" works too
+//! 0x2A4 draw_score   address first
+//! ```
+//!
+//! Addresses are hexadecimal, with or without `0x`. Blank lines and lines
+//! starting with `#` are ignored.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Labels further than this below an address are not used to describe it.
+const MAX_OFFSET: u16 = 0x100;
+
+#[derive(Debug)]
+pub enum SymbolsError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for SymbolsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SymbolsError::Io(err) => write!(f, "{}", err),
+            SymbolsError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for SymbolsError {}
+
+impl From<io::Error> for SymbolsError {
+    fn from(err: io::Error) -> Self {
+        SymbolsError::Io(err)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Symbols {
+    /// The first name given to each address.
+    names: BTreeMap<u16, String>,
+    addresses: HashMap<String, u16>,
+}
+
+impl Symbols {
+    pub fn load(path: &Path) -> Result<Self, SymbolsError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, SymbolsError> {
+        let mut symbols = Self::default();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: String| SymbolsError::Parse { line: number + 1, message };
+
+            let fields: Vec<&str> = match line.strip_prefix(':') {
+                Some(rest) => rest.split_whitespace().collect(),
+                None => line.split_whitespace().rev().collect(),
+            };
+            let [name, address] = fields[..] else {
+                return Err(error(format!("expected ':label ADDR' or 'ADDR label', got '{}'", line)));
+            };
+
+            if !is_label(name) {
+                return Err(error(format!("'{}' is not a valid label", name)));
+            }
+            let digits = address.trim_start_matches("0x").trim_start_matches("0X");
+            let address = u16::from_str_radix(digits, 16)
+                .map_err(|_| error(format!("'{}' is not a hex address", address)))?;
+
+            symbols.insert(name, address).map_err(error)?;
+        }
+
+        Ok(symbols)
+    }
+
+    /// Adds a label, failing if the name is already taken.
+    pub fn insert(&mut self, name: &str, address: u16) -> Result<(), String> {
+        if let Some(existing) = self.addresses.get(name) {
+            return Err(format!("'{}' is already defined as 0x{:04X}", name, existing));
+        }
+        self.addresses.insert(name.to_string(), address);
+        self.names.entry(address).or_insert_with(|| name.to_string());
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.addresses.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.addresses.len()
+    }
+
+    /// The label at exactly `address`.
+    pub fn name(&self, address: u16) -> Option<&str> {
+        self.names.get(&address).map(String::as_str)
+    }
+
+    pub fn address(&self, name: &str) -> Option<u16> {
+        self.addresses.get(name).copied()
+    }
+
+    /// Names `address` relative to the closest label at or below it, e.g.
+    /// `draw_score+6`.
+    pub fn describe(&self, address: u16) -> Option<String> {
+        let (&base, name) = self.names.range(..=address).next_back()?;
+        match address - base {
+            0 => Some(name.clone()),
+            offset if offset <= MAX_OFFSET => Some(format!("{}+{}", name, offset)),
+            _ => None,
+        }
+    }
+
+    /// Labels by address, in address order.
+    pub fn iter(&self) -> impl Iterator<Item = (u16, &str)> {
+        self.names.iter().map(|(&address, name)| (address, name.as_str()))
+    }
+}
+
+/// Label names start with a letter or underscore, followed by letters,
+/// digits, underscores or dashes.
+pub fn is_label(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(text: &str) -> (usize, String) {
+        match Symbols::parse(text).unwrap_err() {
+            SymbolsError::Parse { line, message } => (line, message),
+            SymbolsError::Io(err) => panic!("unexpected I/O error: {}", err),
+        }
+    }
+
+    #[test]
+    fn both_formats_parse() {
+        let symbols = Symbols::parse(
+            "# Pong\n\
+             :main 0x200\n\
+             : draw_score 2A4\n\
+             \n\
+             0x2B0 player-1\n\
+             0X2c0 _ball\n",
+        )
+        .unwrap();
+        assert_eq!(symbols.len(), 4);
+        assert_eq!(symbols.address("main"), Some(0x200));
+        assert_eq!(symbols.address("draw_score"), Some(0x2A4));
+        assert_eq!(symbols.address("player-1"), Some(0x2B0));
+        assert_eq!(symbols.address("_ball"), Some(0x2C0));
+        assert_eq!(symbols.name(0x2A4), Some("draw_score"));
+        assert_eq!(symbols.address("nowhere"), None);
+    }
+
+    #[test]
+    fn the_first_name_for_an_address_is_kept() {
+        let symbols = Symbols::parse(":start 0x200\n:main 0x200").unwrap();
+        assert_eq!(symbols.name(0x200), Some("start"));
+        assert_eq!(symbols.address("main"), Some(0x200));
+    }
+
+    #[test]
+    fn addresses_are_described_by_the_closest_label_below() {
+        let symbols = Symbols::parse(":main 0x200\n:loop 0x210").unwrap();
+        assert_eq!(symbols.describe(0x200).as_deref(), Some("main"));
+        assert_eq!(symbols.describe(0x20E).as_deref(), Some("main+14"));
+        assert_eq!(symbols.describe(0x216).as_deref(), Some("loop+6"));
+        assert_eq!(symbols.describe(0x310).as_deref(), Some("loop+256"));
+        assert_eq!(symbols.describe(0x311), None);
+        assert_eq!(symbols.describe(0x100), None);
+    }
+
+    #[test]
+    fn iteration_is_in_address_order() {
+        let symbols = Symbols::parse("0x300 b\n0x200 a").unwrap();
+        let labels: Vec<(u16, &str)> = symbols.iter().collect();
+        assert_eq!(labels, [(0x200, "a"), (0x300, "b")]);
+    }
+
+    #[test]
+    fn errors_carry_their_line() {
+        assert_eq!(
+            error(":main 0x200\n:main 0x300"),
+            (2, "'main' is already defined as 0x0200".to_string())
+        );
+        assert_eq!(
+            error("\n\n:main"),
+            (3, "expected ':label ADDR' or 'ADDR label', got ':main'".to_string())
+        );
+        assert_eq!(error(":1st 0x200"), (1, "'1st' is not a valid label".to_string()));
+        assert_eq!(error(":main 0x10000"), (1, "'0x10000' is not a hex address".to_string()));
+        assert_eq!(
+            error("0x200 a b"),
+            (1, "expected ':label ADDR' or 'ADDR label', got '0x200 a b'".to_string())
+        );
+    }
+
+    #[test]
+    fn labels() {
+        assert!(is_label("draw_score"));
+        assert!(is_label("_tmp-2"));
+        assert!(!is_label("2fast"));
+        assert!(!is_label("a.b"));
+        assert!(!is_label(""));
+    }
+}
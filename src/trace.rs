@@ -0,0 +1,435 @@
+//! Instruction trace logging.
+//!
+//! Each executed instruction is written as one line, before it runs:
+//!
+//! ```text
+//! 00000004 000000 0208 D01F V=0C080000000000000000000000000000 I=022A SP=00 DT=00 ST=00 M=7B3B60F4 ; DRW  V0, V1, F
+//! ```
+//!
+//! The fields are the instruction count, the frame number, PC, the opcode,
+//! V0-VF as 32 hex digits, I, SP, the delay and sound timers, a checksum of
+//! memory (`--------` unless asked for, as it hashes all of memory for every
+//! instruction), and after ` ; ` the disassembly. Everything before the ` ; ` is
+//! fixed width, so traces from two runs can be compared line by line; see
+//! [`diff`].
+
+use crate::disassembler;
+use crate::emulator::Emulator;
+use crate::expr::parse_number;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::num::ParseIntError;
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+/// Which instructions make it into a trace.
+#[derive(Clone, Debug, Default)]
+pub struct TraceFilter {
+    /// Only instructions whose address is in this range.
+    pub addresses: Option<RangeInclusive<u16>>,
+
+    /// Only instructions executed during these frames.
+    pub frames: Option<RangeInclusive<u64>>,
+}
+
+impl TraceFilter {
+    pub fn matches(&self, emu: &Emulator) -> bool {
+        in_range(&self.addresses, emu.pc) && in_range(&self.frames, emu.frame_count)
+    }
+}
+
+fn in_range<T: PartialOrd>(range: &Option<RangeInclusive<T>>, value: T) -> bool {
+    match range {
+        Some(range) => range.contains(&value),
+        None => true,
+    }
+}
+
+pub struct Trace<W: Write = BufWriter<File>> {
+    out: W,
+    filter: TraceFilter,
+
+    /// Whether lines carry a checksum of memory.
+    memory: bool,
+}
+
+impl Trace {
+    pub fn create(path: &Path, filter: TraceFilter, memory: bool) -> io::Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?), filter, memory))
+    }
+}
+
+impl<W: Write> Trace<W> {
+    pub fn new(out: W, filter: TraceFilter, memory: bool) -> Self {
+        Self { out, filter, memory }
+    }
+
+    /// Logs the instruction `emu` is about to execute, if the filter
+    /// allows it.
+    pub fn record(&mut self, emu: &Emulator) -> io::Result<()> {
+        if self.filter.matches(emu) {
+            writeln!(self.out, "{}", format_line(emu, self.memory))?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Formats the trace line for the instruction at PC, with a checksum of
+/// memory if `memory` is set.
+pub fn format_line(emu: &Emulator, memory: bool) -> String {
+    let opcode = emu.fetch().unwrap_or(0);
+    let (disasm, _) = disassembler::disassemble_at(&emu.memory, emu.pc as usize);
+    let registers: String = emu.v.iter().map(|v| format!("{:02X}", v)).collect();
+    let checksum = if memory {
+        format!("{:08X}", memory_checksum(&emu.memory))
+    } else {
+        "--------".to_string()
+    };
+
+    format!(
+        "{:08} {:06} {:04X} {:04X} V={} I={:04X} SP={:02X} DT={:02X} ST={:02X} M={} ; {}",
+        emu.instruction_count,
+        emu.frame_count,
+        emu.pc,
+        opcode,
+        registers,
+        emu.i,
+        emu.sp,
+        emu.delay_timer,
+        emu.sound_timer,
+        checksum,
+        disasm
+    )
+}
+
+/// 32-bit FNV-1a hash of memory, so traces can show that memory changed
+/// without listing it.
+pub fn memory_checksum(memory: &[u8]) -> u32 {
+    memory
+        .iter()
+        .fold(0x811C_9DC5, |hash, &byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193))
+}
+
+/// The machine state recorded on one trace line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceLine {
+    pub instruction: u64,
+    pub frame: u64,
+    pub pc: u16,
+    pub opcode: u16,
+    pub v: [u8; 16],
+    pub i: u16,
+    pub sp: u8,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+
+    /// Memory checksum, if the trace was made with one.
+    pub memory: Option<u32>,
+    pub disassembly: String,
+}
+
+impl TraceLine {
+    /// Parses a line in the format written by [`format_line`], saying which
+    /// field is wrong if it is not.
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let (state, disassembly) = line.split_once(" ; ").unwrap_or((line, ""));
+        let mut fields = state.split_whitespace();
+        let mut next = |name: &str| fields.next().ok_or_else(|| format!("missing {}", name));
+
+        let instruction = decimal(next("instruction count")?, "instruction count")?;
+        let frame = decimal(next("frame")?, "frame")?;
+        let pc = hex(next("PC")?, "", "PC", u16::from_str_radix)?;
+        let opcode = hex(next("opcode")?, "", "opcode", u16::from_str_radix)?;
+
+        let registers = next("registers")?;
+        let digits = registers
+            .strip_prefix("V=")
+            .filter(|digits| digits.len() == 32 && digits.is_ascii())
+            .ok_or_else(|| format!("invalid registers '{}'", registers))?;
+        let mut v = [0; 16];
+        for (reg, value) in v.iter_mut().enumerate() {
+            *value = hex(&digits[reg * 2..reg * 2 + 2], "", &format!("V{:X}", reg), u8::from_str_radix)?;
+        }
+
+        let i = hex(next("I")?, "I=", "I", u16::from_str_radix)?;
+        let sp = hex(next("SP")?, "SP=", "SP", u8::from_str_radix)?;
+        let delay_timer = hex(next("DT")?, "DT=", "DT", u8::from_str_radix)?;
+        let sound_timer = hex(next("ST")?, "ST=", "ST", u8::from_str_radix)?;
+        let memory = match next("memory checksum")? {
+            "M=--------" => None,
+            text => Some(hex(text, "M=", "memory checksum", u32::from_str_radix)?),
+        };
+        if let Some(extra) = fields.next() {
+            return Err(format!("unexpected '{}'", extra));
+        }
+
+        Ok(Self {
+            instruction,
+            frame,
+            pc,
+            opcode,
+            v,
+            i,
+            sp,
+            delay_timer,
+            sound_timer,
+            memory,
+            disassembly: disassembly.to_string(),
+        })
+    }
+
+    /// Describes how the machine state differs from `other`, one entry per
+    /// field. The instruction and frame counters are not compared, as other
+    /// emulators may count them differently.
+    pub fn differences(&self, other: &TraceLine) -> Vec<String> {
+        let mut differences = Vec::new();
+        let mut compare = |name: String, a: String, b: String| {
+            if a != b {
+                differences.push(format!("{}: {} vs {}", name, a, b));
+            }
+        };
+
+        compare("PC".into(), format!("{:04X}", self.pc), format!("{:04X}", other.pc));
+        compare("opcode".into(), format!("{:04X}", self.opcode), format!("{:04X}", other.opcode));
+        for reg in 0..16 {
+            compare(format!("V{:X}", reg), format!("{:02X}", self.v[reg]), format!("{:02X}", other.v[reg]));
+        }
+        compare("I".into(), format!("{:04X}", self.i), format!("{:04X}", other.i));
+        compare("SP".into(), format!("{:02X}", self.sp), format!("{:02X}", other.sp));
+        compare("DT".into(), format!("{:02X}", self.delay_timer), format!("{:02X}", other.delay_timer));
+        compare("ST".into(), format!("{:02X}", self.sound_timer), format!("{:02X}", other.sound_timer));
+        // Only traces that both have checksums can be compared on memory.
+        if let (Some(a), Some(b)) = (self.memory, other.memory) {
+            compare("memory checksum".into(), format!("{:08X}", a), format!("{:08X}", b));
+        }
+        differences
+    }
+}
+
+/// Where two traces first disagree.
+#[derive(Debug)]
+pub struct Divergence {
+    /// Zero-based index of the first line that differs.
+    pub line: usize,
+
+    /// What differs on that line; empty if one trace simply ended.
+    pub differences: Vec<String>,
+}
+
+/// A line that is not in the trace format.
+#[derive(Debug)]
+pub struct BadLine {
+    /// 0 for the first trace passed to [`diff`], 1 for the second.
+    pub trace: usize,
+
+    /// One-based line number.
+    pub line: usize,
+
+    /// What is wrong with it.
+    pub reason: String,
+}
+
+fn decimal(text: &str, name: &str) -> Result<u64, String> {
+    text.parse()
+        .ok()
+        .filter(|_| text.bytes().all(|byte| byte.is_ascii_digit()))
+        .ok_or_else(|| format!("invalid {} '{}'", name, text))
+}
+
+/// Parses a hex field such as `I=022A`, rejecting digits that do not fit
+/// the field rather than truncating them.
+fn hex<T>(text: &str, key: &str, name: &str, parse: fn(&str, u32) -> Result<T, ParseIntError>) -> Result<T, String> {
+    text.strip_prefix(key)
+        .filter(|digits| digits.bytes().all(|byte| byte.is_ascii_hexdigit()))
+        .and_then(|digits| parse(digits, 16).ok())
+        .ok_or_else(|| format!("invalid {} '{}'", name, text))
+}
+
+/// Compares two traces line by line and returns the first divergence, or
+/// `None` if they agree.
+pub fn diff(a: &[&str], b: &[&str]) -> Result<Option<Divergence>, BadLine> {
+    for line in 0..a.len().max(b.len()) {
+        let (Some(left), Some(right)) = (a.get(line), b.get(line)) else {
+            return Ok(Some(Divergence {
+                line,
+                differences: Vec::new(),
+            }));
+        };
+
+        let bad = |trace: usize| move |reason| BadLine { trace, line: line + 1, reason };
+        let left = TraceLine::parse(left).map_err(bad(0))?;
+        let right = TraceLine::parse(right).map_err(bad(1))?;
+        let differences = left.differences(&right);
+        if !differences.is_empty() {
+            return Ok(Some(Divergence { line, differences }));
+        }
+    }
+    Ok(None)
+}
+
+/// Parses `START-END` or `START-` (open ended), each bound a number as
+/// accepted by [`parse_number`].
+fn parse_range(text: &str) -> Result<(i64, Option<i64>), String> {
+    let (start, end) = text
+        .split_once('-')
+        .ok_or_else(|| format!("expected START-END, got '{}'", text))?;
+    let number = |part: &str| parse_number(part.trim()).ok_or_else(|| format!("invalid number '{}'", part.trim()));
+
+    let start = number(start)?;
+    let end = if end.trim().is_empty() { None } else { Some(number(end)?) };
+    if end.is_some_and(|end| end < start) {
+        return Err(format!("range '{}' ends before it starts", text));
+    }
+    Ok((start, end))
+}
+
+/// Parses an address range such as `0x200-0x2FF` for `--trace-addr`.
+pub fn parse_address_range(text: &str) -> Result<RangeInclusive<u16>, String> {
+    let (start, end) = parse_range(text)?;
+    let address = |value: i64| u16::try_from(value).map_err(|_| format!("address {} is out of range", value));
+    Ok(address(start)?..=address(end.unwrap_or(u16::MAX as i64))?)
+}
+
+/// Parses a frame range such as `100-200` or `100-` for `--trace-frames`.
+pub fn parse_frame_range(text: &str) -> Result<RangeInclusive<u64>, String> {
+    let (start, end) = parse_range(text)?;
+    let frame = |value: i64| u64::try_from(value).map_err(|_| format!("frame {} is out of range", value));
+    Ok(frame(start)?..=frame(end.unwrap_or(i64::MAX))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Trace lines for the first `count` instructions of the IBM logo ROM.
+    fn trace(count: usize) -> Vec<String> {
+        let mut emu = Emulator::new();
+        emu.load_rom(include_bytes!("../roms/ibm_logo.ch8"));
+        let mut lines = Vec::new();
+        for _ in 0..count {
+            lines.push(format_line(&emu, true));
+            emu.cycle().unwrap();
+        }
+        lines
+    }
+
+    fn lines(trace: &[String]) -> Vec<&str> {
+        trace.iter().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn lines_parse_back() {
+        let mut emu = Emulator::new();
+        emu.load_rom(&[0x6A, 0x2B]);
+        emu.v[0xF] = 0xEE;
+        emu.i = 0x1234;
+        emu.sp = 3;
+        emu.delay_timer = 9;
+        emu.sound_timer = 0x10;
+        emu.instruction_count = 17;
+        emu.frame_count = 2;
+
+        let line = TraceLine::parse(&format_line(&emu, true)).unwrap();
+        assert_eq!((line.instruction, line.frame), (17, 2));
+        assert_eq!((line.pc, line.opcode), (0x200, 0x6A2B));
+        assert_eq!(line.v[0xF], 0xEE);
+        assert_eq!((line.i, line.sp, line.delay_timer, line.sound_timer), (0x1234, 3, 9, 0x10));
+        assert_eq!(line.memory, Some(memory_checksum(&emu.memory)));
+        assert_eq!(line.disassembly, "LD   VA, 2B");
+    }
+
+    #[test]
+    fn identical_traces_agree() {
+        let a = trace(20);
+        let b = trace(20);
+        assert!(diff(&lines(&a), &lines(&b)).unwrap().is_none());
+    }
+
+    #[test]
+    fn counters_are_not_compared() {
+        let a = trace(5);
+        let b: Vec<String> = a.iter().map(|line| format!("99999999 999999 {}", &line[16..])).collect();
+        assert!(diff(&lines(&a), &lines(&b)).unwrap().is_none());
+    }
+
+    #[test]
+    fn register_divergence_is_found() {
+        let a = trace(20);
+        let mut b = a.clone();
+        // V0 is the first register after "V=".
+        let at = b[12].find("V=").unwrap() + 2;
+        b[12].replace_range(at..at + 2, "7F");
+
+        let divergence = diff(&lines(&a), &lines(&b)).unwrap().unwrap();
+        assert_eq!(divergence.line, 12);
+        let expected = format!("V0: {} vs 7F", &a[12][at..at + 2]);
+        assert_eq!(divergence.differences, [expected]);
+    }
+
+    #[test]
+    fn length_mismatch_is_a_divergence() {
+        let a = trace(20);
+        let b = trace(15);
+        let divergence = diff(&lines(&a), &lines(&b)).unwrap().unwrap();
+        assert_eq!(divergence.line, 15);
+        assert!(divergence.differences.is_empty());
+    }
+
+    #[test]
+    fn malformed_lines_are_reported() {
+        let a = trace(5);
+        let mut b = a.clone();
+        b[3] = "not a trace line".to_string();
+        let bad = diff(&lines(&a), &lines(&b)).unwrap_err();
+        assert_eq!((bad.trace, bad.line), (1, 4));
+
+        assert_eq!(bad.reason, "invalid instruction count 'not'");
+
+        let parse = |line: String| TraceLine::parse(&line).unwrap_err();
+        assert_eq!(parse(String::new()), "missing instruction count");
+        assert!(parse(a[0].replace("V=", "V=0")).starts_with("invalid registers"));
+        assert!(parse(a[0].replace("I=", "J=")).starts_with("invalid I 'J="));
+        assert_eq!(parse(a[0].replace(" ; ", " extra ; ")), "unexpected 'extra'");
+    }
+
+    #[test]
+    fn oversized_fields_are_rejected_instead_of_truncated() {
+        let line = trace(1).remove(0);
+        let parse = |from: &str, to: &str| TraceLine::parse(&line.replacen(from, to, 1)).unwrap_err();
+        assert_eq!(parse("I=0000", "I=10000"), "invalid I 'I=10000'");
+        assert_eq!(parse("SP=00", "SP=100"), "invalid SP 'SP=100'");
+        assert_eq!(parse("DT=00", "DT=+1"), "invalid DT 'DT=+1'");
+        assert_eq!(parse(" 0200 ", " 10200 "), "invalid PC '10200'");
+        assert_eq!(parse("V=00", "V=0G"), "invalid V0 '0G'");
+        assert_eq!(parse("00000000 ", "-0000001 "), "invalid instruction count '-0000001'");
+    }
+
+    #[test]
+    fn memory_checksums_are_optional() {
+        let mut emu = Emulator::new();
+        emu.load_rom(include_bytes!("../roms/ibm_logo.ch8"));
+        let without = format_line(&emu, false);
+        assert!(without.contains(" M=-------- ; "));
+        assert_eq!(TraceLine::parse(&without).unwrap().memory, None);
+
+        // A trace without checksums is compared on everything else.
+        emu.memory[0x300] = 1;
+        let with = format_line(&emu, true);
+        assert!(diff(&[&without], &[&with]).unwrap().is_none());
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(parse_address_range("0x200-0x2FF"), Ok(0x200..=0x2FF));
+        assert_eq!(parse_address_range("0x300-"), Ok(0x300..=0xFFFF));
+        assert_eq!(parse_frame_range("600-900"), Ok(600..=900));
+        assert_eq!(parse_frame_range("600-"), Ok(600..=i64::MAX as u64));
+        assert_eq!(parse_frame_range("9-1"), Err("range '9-1' ends before it starts".to_string()));
+        assert_eq!(parse_address_range("0x200"), Err("expected START-END, got '0x200'".to_string()));
+        assert_eq!(parse_address_range("0-0x10000"), Err("address 65536 is out of range".to_string()));
+    }
+}
@@ -1,4 +1,15 @@
-use crate::emulator::Emulator;
+use crate::assembler;
+use crate::breakpoint::{Breakpoint, Condition};
+use crate::database::RomInfo;
+use crate::emulator::{Emulator, FrameOutput, RunUntil, StopReason};
+use crate::expr::Expr;
+use crate::fault::Fault;
+use crate::history::History;
+use crate::rewind::Rewind;
+use crate::savestate::{self, SLOT_COUNT};
+use crate::symbols::Symbols;
+use crate::trace::Trace;
+use crate::watchpoint::Watchpoint;
 use crate::disassembler;
 use crossterm::{
     event::{self, Event, KeyCode, KeyEvent},
@@ -13,18 +24,953 @@ use ratatui::{
     widgets::{Block, Borders, Paragraph},
     Frame, Terminal,
 };
+use std::collections::{HashMap, HashSet};
+use std::fs;
 use std::io;
-use std::time::{Duration, Instant};
-
-const DISPLAY_WIDTH: usize = 64;
-const DISPLAY_HEIGHT: usize = 32;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How long a status message stays on screen.
+const MESSAGE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Rewind history: up to two minutes at 60 frames per second, within 64 MiB.
+const REWIND_FRAMES: usize = 120 * 60;
+const REWIND_BUDGET: usize = 64 * 1024 * 1024;
+
+/// Terminals only report key repeats, not releases, so rewinding continues
+/// for this long after the last Backspace event to bridge repeat gaps.
+const REWIND_HOLD: Duration = Duration::from_millis(150);
+
+/// How often `--watch` checks the ROM file for changes.
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Instructions kept for stepping backwards, about three minutes at the
+/// default speed.
+const HISTORY_INSTRUCTIONS: usize = 100_000;
+
+/// Instructions shown in the disassembly panel.
+const DISASM_ROWS: usize = 12;
+
+/// Instructions shown above PC while the disassembly panel follows it.
+const DISASM_CONTEXT: usize = 3;
+
+/// Bytes per row of the memory panel.
+const MEMORY_ROW: usize = 16;
+
+// CHIP-8 keypad mapping (0-F)
+// Original:  1 2 3 C    Keyboard:  1 2 3 4
+//            4 5 6 D               Q W E R
+//            7 8 9 E               A S D F
+//            A 0 B F               Z X C V
+const KEY_MAP: [(char, usize); 16] = [
+    ('1', 0x1),
+    ('2', 0x2),
+    ('3', 0x3),
+    ('4', 0xC),
+    ('q', 0x4),
+    ('w', 0x5),
+    ('e', 0x6),
+    ('r', 0xD),
+    ('a', 0x7),
+    ('s', 0x8),
+    ('d', 0x9),
+    ('f', 0xE),
+    ('z', 0xA),
+    ('x', 0x0),
+    ('c', 0xB),
+    ('v', 0xF),
+];
+
+/// Emulated frames a byte stays highlighted in the memory panel after it
+/// was written.
+const WRITE_HIGHLIGHT_FRAMES: u8 = 60;
 
 enum UiMode {
     Playing,
     Inspector,
 }
 
-pub fn run(mut emulator: Emulator, cycles_per_frame: u32) -> Result<(), io::Error> {
+/// An editable entry of the CPU panel.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CpuField {
+    Pc,
+    I,
+    Sp,
+    Dt,
+    St,
+    V(u8),
+    Key(u8),
+}
+
+impl CpuField {
+    /// Every field, in the order the cursor moves through them.
+    fn all() -> Vec<CpuField> {
+        let mut fields = vec![CpuField::Pc, CpuField::I, CpuField::Sp, CpuField::Dt, CpuField::St];
+        fields.extend((0..16).map(CpuField::V));
+        fields.extend((0..16).map(CpuField::Key));
+        fields
+    }
+
+    fn name(self) -> String {
+        match self {
+            CpuField::Pc => "PC".to_string(),
+            CpuField::I => "I".to_string(),
+            CpuField::Sp => "SP".to_string(),
+            CpuField::Dt => "DT".to_string(),
+            CpuField::St => "ST".to_string(),
+            CpuField::V(reg) => format!("V{:X}", reg),
+            CpuField::Key(key) => format!("key {:X}", key),
+        }
+    }
+}
+
+/// A scrolled disassembly panel: the first listed address and the selected
+/// one. It only applies while PC stays where it was when the view was set,
+/// so the panel jumps back to PC once execution moves on.
+#[derive(Clone, Copy)]
+struct DisasmView {
+    pc: u16,
+    top: usize,
+    cursor: usize,
+}
+
+/// A change made by hand from the inspector, remembered so it can be undone.
+enum Edit {
+    Cpu(CpuField, u16),
+    Memory(usize, u8),
+    /// An assembled instruction, with the bytes it replaced.
+    Patch(usize, Vec<u8>),
+}
+
+struct App {
+    emulator: Emulator,
+    mode: UiMode,
+
+    /// The last fault raised by the emulator, shown in Inspector Mode until
+    /// the next successful step or a reset.
+    fault: Option<Fault>,
+
+    /// The breakpoint or watchpoint execution last stopped on, shown in
+    /// Inspector Mode until execution moves on.
+    stop: Option<StopReason>,
+
+    rom_path: PathBuf,
+
+    /// Save state slot used by F5/F9, 1 to `SLOT_COUNT`.
+    slot: u8,
+
+    message: Option<(String, Instant)>,
+
+    rewind: Rewind,
+
+    /// Set while Backspace is held during gameplay.
+    rewinding_until: Option<Instant>,
+
+    /// Where the disassembly panel has been scrolled to; `None` while it
+    /// follows PC.
+    disasm_view: Option<DisasmView>,
+
+    /// Views left by following jumps and calls, most recent last.
+    disasm_trail: Vec<DisasmView>,
+
+    /// Text typed at the inspector's `:` command line, while it is open.
+    prompt: Option<String>,
+
+    /// Why the last command was rejected; shown until the next keystroke.
+    prompt_error: Option<String>,
+
+    /// Whether the memory panel replaces the display in Inspector Mode.
+    show_memory: bool,
+
+    /// Selected byte in the memory panel.
+    memory_cursor: usize,
+
+    /// Hex digits typed so far while editing memory at the cursor.
+    memory_edit: Option<String>,
+
+    /// Frames left to highlight recently written bytes of memory.
+    recent_writes: HashMap<usize, u8>,
+
+    /// Selected CPU panel field while the panel has focus (Tab).
+    cpu_cursor: Option<usize>,
+
+    /// Hex digits typed so far while editing the selected CPU field.
+    cpu_edit: Option<String>,
+
+    /// Keypad keys held down from the CPU panel, regardless of the keyboard.
+    forced_keys: [bool; 16],
+
+    /// Previous values of inspector edits, most recent last.
+    undo: Vec<Edit>,
+
+    /// Bytes written by assembling instructions from the inspector, and
+    /// their patched values. A byte is only shown as patched while it still
+    /// holds that value.
+    patched: HashMap<usize, u8>,
+
+    /// Log of executed instructions, from `--trace`.
+    trace: Option<Trace>,
+
+    /// Labels from `--symbols`.
+    symbols: Symbols,
+
+    /// The ROM's entry in the ROM database.
+    info: Option<RomInfo>,
+
+    /// Display colours, from the ROM database or [`PLANE_COLORS`].
+    palette: [Color; 16],
+
+    /// Reloads the ROM when it changes, from `--watch`.
+    watch: Option<Watch>,
+
+    /// Modification time of the ROM file when it was last loaded.
+    rom_modified: Option<SystemTime>,
+
+    /// When `--watch` last looked at the ROM file.
+    watch_checked: Instant,
+}
+
+/// A freshly loaded ROM: the emulator running it, its symbols and its entry
+/// in the ROM database.
+pub type Loaded = (Emulator, Symbols, Option<RomInfo>);
+
+/// Reloads the ROM whenever its file changes on disk, for `--watch`.
+pub struct Watch {
+    /// Builds a fresh emulator, and the symbols and ROM database entry to go
+    /// with it, from the file.
+    pub load: Box<dyn Fn() -> Result<Loaded, String>>,
+
+    /// Whether breakpoints and watchpoints set in the inspector carry over to
+    /// the reloaded ROM.
+    pub keep_breakpoints: bool,
+}
+
+impl App {
+    fn new(
+        mut emulator: Emulator,
+        rom_path: &Path,
+        trace: Option<Trace>,
+        symbols: Symbols,
+        info: Option<RomInfo>,
+        watch: Option<Watch>,
+    ) -> Self {
+        emulator.history = History::new(HISTORY_INSTRUCTIONS);
+        Self {
+            emulator,
+            mode: UiMode::Playing,
+            fault: None,
+            stop: None,
+            rom_path: rom_path.to_path_buf(),
+            slot: 1,
+            message: None,
+            rewind: Rewind::new(REWIND_FRAMES, REWIND_BUDGET),
+            rewinding_until: None,
+            disasm_view: None,
+            disasm_trail: Vec::new(),
+            prompt: None,
+            prompt_error: None,
+            show_memory: false,
+            memory_cursor: 0x200,
+            memory_edit: None,
+            recent_writes: HashMap::new(),
+            cpu_cursor: None,
+            cpu_edit: None,
+            forced_keys: [false; 16],
+            undo: Vec::new(),
+            patched: HashMap::new(),
+            trace,
+            symbols,
+            palette: palette(info.as_ref()),
+            info,
+            watch,
+            rom_modified: modified_time(rom_path),
+            watch_checked: Instant::now(),
+        }
+    }
+
+    /// Reloads the ROM if `--watch` is on and the file changed since it was
+    /// last loaded.
+    fn check_watched_rom(&mut self) {
+        let Some(watch) = &self.watch else {
+            return;
+        };
+        if self.watch_checked.elapsed() < WATCH_INTERVAL {
+            return;
+        }
+        self.watch_checked = Instant::now();
+
+        let modified = modified_time(&self.rom_path);
+        if modified == self.rom_modified {
+            return;
+        }
+        self.rom_modified = modified;
+
+        match (watch.load)() {
+            Ok((emulator, symbols, info)) => self.reload(emulator, symbols, info),
+            // Keep running the old ROM; saving a fixed file tries again.
+            Err(err) => self.set_message(format!("Reload failed: {}", err)),
+        }
+    }
+
+    /// Swaps in a freshly loaded emulator, keeping the UI as it is.
+    fn reload(&mut self, mut emulator: Emulator, symbols: Symbols, info: Option<RomInfo>) {
+        if self.watch.as_ref().is_some_and(|watch| watch.keep_breakpoints) {
+            // Named breakpoints come from the source, which has its own now.
+            for (&addr, breakpoint) in &self.emulator.breakpoints {
+                if breakpoint.name.is_none() {
+                    let breakpoint = Breakpoint {
+                        hits: 0,
+                        ..breakpoint.clone()
+                    };
+                    emulator.breakpoints.entry(addr).or_insert(breakpoint);
+                }
+            }
+            emulator.watchpoints = std::mem::take(&mut self.emulator.watchpoints);
+        }
+        emulator.paused = self.emulator.paused;
+        emulator.history = History::new(HISTORY_INSTRUCTIONS);
+
+        let size = emulator.rom.len();
+        self.emulator = emulator;
+        self.symbols = symbols;
+        self.palette = palette(info.as_ref());
+        self.info = info;
+        self.fault = None;
+        self.stop = None;
+        self.rewind.clear();
+        self.forget_edits();
+        self.recent_writes.clear();
+        // The new ROM may run on a platform with less memory.
+        self.move_memory_cursor(0);
+        self.disasm_view = None;
+        self.disasm_trail.clear();
+        self.set_message(format!("Reloaded {} ({} bytes)", self.rom_path.display(), size));
+    }
+
+    /// Disassembles the instruction at `addr`, naming jump, call and `LD I`
+    /// targets by their labels.
+    fn disassemble(&self, addr: usize) -> (String, usize) {
+        let label = |target: u16| self.symbols.name(target).map(str::to_string);
+        disassembler::disassemble_at_labeled(&self.emulator.memory, addr, &label)
+    }
+
+    /// `0x02A4`, followed by the nearest label if there is one.
+    fn describe_address(&self, addr: u16) -> String {
+        match self.symbols.describe(addr) {
+            Some(label) => format!("0x{:04X} {}", addr, label),
+            None => format!("0x{:04X}", addr),
+        }
+    }
+
+    /// Runs the emulator through `run`, logging each instruction to the
+    /// trace file if there is one. A write error stops tracing.
+    fn traced<T>(&mut self, run: impl FnOnce(&mut Emulator, &mut dyn FnMut(&Emulator)) -> T) -> T {
+        let mut error = None;
+        let result = match self.trace.as_mut() {
+            Some(trace) => {
+                let result = run(&mut self.emulator, &mut |emu| {
+                    if error.is_none() {
+                        error = trace.record(emu).err();
+                    }
+                });
+                // Flushed every time so the file is complete even if term-8
+                // is killed rather than quit.
+                if error.is_none() {
+                    error = trace.flush().err();
+                }
+                result
+            }
+            None => run(&mut self.emulator, &mut |_| {}),
+        };
+
+        if let Some(err) = error {
+            self.trace = None;
+            self.set_message(format!("Tracing stopped: {}", err));
+        }
+        result
+    }
+
+    fn cycle(&mut self) -> Result<(), Fault> {
+        self.traced(|emulator, observe| emulator.cycle_with(observe))
+    }
+
+    fn run_frame(&mut self, keypad: [bool; 16]) -> Result<FrameOutput, Fault> {
+        self.traced(|emulator, observe| emulator.run_frame_with(keypad, observe))
+    }
+
+    fn cpu_value(&self, field: CpuField) -> u16 {
+        let emulator = &self.emulator;
+        match field {
+            CpuField::Pc => emulator.pc,
+            CpuField::I => emulator.i,
+            CpuField::Sp => emulator.sp as u16,
+            CpuField::Dt => emulator.delay_timer as u16,
+            CpuField::St => emulator.sound_timer as u16,
+            CpuField::V(reg) => emulator.v[reg as usize] as u16,
+            CpuField::Key(key) => self.forced_keys[key as usize] as u16,
+        }
+    }
+
+    /// Sets a CPU field, rejecting values it cannot hold.
+    fn set_cpu_value(&mut self, field: CpuField, value: u16) -> Result<(), String> {
+        let byte = || u8::try_from(value).map_err(|_| format!("{} only holds 00-FF", field.name()));
+        let emulator = &mut self.emulator;
+        match field {
+            CpuField::Pc if value as usize + 1 >= emulator.memory.len() => {
+                return Err(format!("PC 0x{:04X} is outside memory", value));
+            }
+            CpuField::Pc => emulator.pc = value,
+            CpuField::I => emulator.i = value,
+            CpuField::Sp if value as usize > emulator.stack.len() => {
+                return Err(format!("SP only goes up to {:X}", emulator.stack.len()));
+            }
+            CpuField::Sp => emulator.sp = value as u8,
+            CpuField::Dt => emulator.delay_timer = byte()?,
+            CpuField::St => emulator.sound_timer = byte()?,
+            CpuField::V(reg) => emulator.v[reg as usize] = byte()?,
+            CpuField::Key(key) => self.forced_keys[key as usize] = value != 0,
+        }
+        Ok(())
+    }
+
+    /// Sets a CPU field from the inspector, recording the old value for undo.
+    fn edit_cpu(&mut self, field: CpuField, value: u16) {
+        let old = self.cpu_value(field);
+        match self.set_cpu_value(field, value) {
+            Ok(()) => {
+                self.undo.push(Edit::Cpu(field, old));
+                self.set_message(format!("{} = {:X}", field.name(), value));
+            }
+            Err(error) => self.set_message(error),
+        }
+    }
+
+    /// Drops undo history and patch records once memory has been replaced,
+    /// as they describe bytes that are no longer there.
+    fn forget_edits(&mut self) {
+        self.undo.clear();
+        self.patched.clear();
+    }
+
+    fn undo_edit(&mut self) {
+        match self.undo.pop() {
+            Some(Edit::Cpu(field, old)) => {
+                // The value was valid when it was replaced, so this cannot fail.
+                let _ = self.set_cpu_value(field, old);
+                self.set_message(format!("Undo: {} = {:X}", field.name(), old));
+            }
+            Some(Edit::Memory(addr, old)) => {
+                if let Some(byte) = self.emulator.memory.get_mut(addr) {
+                    *byte = old;
+                }
+                self.set_message(format!("Undo: [0x{:04X}] = {:02X}", addr, old));
+            }
+            Some(Edit::Patch(addr, old)) => {
+                for (at, &byte) in (addr..).zip(&old) {
+                    if let Some(slot) = self.emulator.memory.get_mut(at) {
+                        *slot = byte;
+                    }
+                    self.patched.remove(&at);
+                }
+                self.set_message(format!("Undo: patch at 0x{:04X}", addr));
+            }
+            None => self.set_message("Nothing to undo"),
+        }
+    }
+
+    fn selected_cpu_field(&self) -> Option<CpuField> {
+        self.cpu_cursor.and_then(|index| CpuField::all().get(index).copied())
+    }
+
+    fn handle_cpu_edit(&mut self, key: KeyEvent) {
+        let Some(digits) = self.cpu_edit.as_mut() else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Esc => self.cpu_edit = None,
+            KeyCode::Backspace => {
+                digits.pop();
+            }
+            KeyCode::Char(c) if c.is_ascii_hexdigit() && digits.len() < 4 => {
+                digits.push(c.to_ascii_uppercase());
+            }
+            KeyCode::Enter => {
+                let digits = self.cpu_edit.take().unwrap_or_default();
+                if let (Some(field), Ok(value)) = (self.selected_cpu_field(), u16::from_str_radix(&digits, 16)) {
+                    self.edit_cpu(field, value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Runs one instruction, as the inspector's step command.
+    fn step(&mut self) {
+        self.emulator.run_until = None;
+        self.emulator.resume();
+        match self.cycle() {
+            Ok(()) => self.fault = None,
+            Err(fault) => self.fault = Some(fault),
+        }
+        self.stop = self.emulator.stop_reason.take();
+        self.note_writes();
+    }
+
+    fn step_back(&mut self) {
+        self.emulator.run_until = None;
+        if self.emulator.step_back() {
+            self.fault = None;
+            self.stop = None;
+            self.set_message(format!(
+                "Stepped back to 0x{:04X} ({} instructions left)",
+                self.emulator.pc,
+                self.emulator.history.len()
+            ));
+        } else {
+            self.set_message("No more execution history");
+        }
+        self.note_writes();
+    }
+
+    fn reverse_continue(&mut self) {
+        self.emulator.run_until = None;
+        self.fault = None;
+        self.stop = None;
+        match self.emulator.reverse_continue() {
+            Some(addr) => self.stop = Some(StopReason::Breakpoint(addr)),
+            None => self.set_message(format!("Reached the start of the history at 0x{:04X}", self.emulator.pc)),
+        }
+        self.note_writes();
+    }
+
+    /// Runs up to `count` instructions, stopping early at a fault,
+    /// breakpoint or watchpoint.
+    fn step_count(&mut self, count: u32) -> u32 {
+        let mut executed = 0;
+        self.stop = None;
+        self.emulator.run_until = None;
+        self.emulator.resume();
+        while executed < count {
+            executed += 1;
+            match self.cycle() {
+                Ok(()) => self.fault = None,
+                Err(fault) => {
+                    self.fault = Some(fault);
+                    break;
+                }
+            }
+            if let Some(reason) = self.emulator.stop_reason.take() {
+                self.stop = Some(reason);
+                break;
+            }
+        }
+        self.note_writes();
+        executed
+    }
+
+    /// Runs until `target` is reached, a breakpoint or watchpoint stops
+    /// execution first, or the user pauses.
+    fn run_until(&mut self, target: RunUntil) {
+        self.emulator.run_until = Some(target);
+        self.emulator.paused = false;
+        self.emulator.resume();
+        self.stop = None;
+    }
+
+    /// Steps over a `2NNN` call by running until it returns; any other
+    /// instruction is simply stepped.
+    fn step_over(&mut self) {
+        // A call in the last word of memory has nowhere to return to, so
+        // stepping it shows the fault its return would raise.
+        let next = self.emulator.pc.checked_add(2);
+        match (self.emulator.fetch(), next) {
+            (Ok(opcode), Some(addr)) if opcode & 0xF000 == 0x2000 => self.run_until(RunUntil::Return {
+                addr,
+                sp: self.emulator.sp,
+            }),
+            _ => self.step(),
+        }
+    }
+
+    fn step_out(&mut self) {
+        if self.emulator.sp == 0 {
+            self.set_message("Not inside a subroutine");
+        } else {
+            self.run_until(RunUntil::StackBelow(self.emulator.sp));
+        }
+    }
+
+    fn run_to_cursor(&mut self) {
+        let addr = self.disasm_view().cursor as u16;
+        self.run_until(RunUntil::Address(addr));
+    }
+
+    /// Runs the rest of the current frame and ticks the timers once, staying
+    /// paused.
+    fn advance_frame(&mut self) {
+        let mut keypad = self.forced_keys;
+        for (held, &down) in keypad.iter_mut().zip(&self.emulator.keypad) {
+            *held |= down;
+        }
+
+        self.stop = None;
+        self.emulator.run_until = None;
+        self.emulator.resume();
+        match self.run_frame(keypad) {
+            Ok(output) => {
+                self.fault = None;
+                self.stop = output.stop;
+                self.rewind.push(savestate::encode(&self.emulator));
+            }
+            Err(fault) => self.fault = Some(fault),
+        }
+        self.note_writes();
+    }
+
+    /// Ages the memory panel's write highlights by one step and marks the
+    /// bytes written since the last call.
+    fn note_writes(&mut self) {
+        self.recent_writes.retain(|_, age| {
+            *age -= 1;
+            *age > 0
+        });
+        for addr in self.emulator.history.take_writes() {
+            self.recent_writes.insert(addr, WRITE_HIGHLIGHT_FRAMES);
+        }
+    }
+
+    fn move_memory_cursor(&mut self, delta: isize) {
+        let last = self.emulator.memory.len() as isize - 1;
+        self.memory_cursor = (self.memory_cursor as isize + delta).clamp(0, last) as usize;
+    }
+
+    fn handle_memory_edit(&mut self, key: KeyEvent) {
+        let Some(digits) = self.memory_edit.as_mut() else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Esc => self.memory_edit = None,
+            KeyCode::Backspace => {
+                digits.pop();
+            }
+            KeyCode::Enter => {
+                let digits = self.memory_edit.take().unwrap_or_default();
+                if !digits.is_empty() {
+                    self.poke_memory(&digits);
+                }
+            }
+            KeyCode::Char(c) if c.is_ascii_hexdigit() => {
+                digits.push(c.to_ascii_uppercase());
+                if digits.len() == 2 {
+                    let digits = std::mem::take(digits);
+                    self.poke_memory(&digits);
+                    self.move_memory_cursor(1);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn poke_memory(&mut self, digits: &str) {
+        if let Ok(value) = u8::from_str_radix(digits, 16) {
+            let addr = self.memory_cursor;
+            let Some(byte) = self.emulator.memory.get_mut(addr) else {
+                return;
+            };
+            self.undo.push(Edit::Memory(addr, *byte));
+            *byte = value;
+            self.recent_writes.insert(addr, WRITE_HIGHLIGHT_FRAMES);
+        }
+    }
+
+    /// Assembles `text` and writes it over the instruction at the
+    /// disassembly cursor.
+    fn patch_instruction(&mut self, text: &str) -> Result<String, String> {
+        if !self.emulator.paused {
+            return Err("pause execution to patch code".to_string());
+        }
+        let addr = self.disasm_view().cursor;
+        let bytes = assembler::assemble_instruction(text, &|name| self.symbols.address(name))?;
+        let end = addr + bytes.len();
+        if end > self.emulator.memory.len() {
+            return Err(format!("0x{:04X} is too close to the end of memory", addr));
+        }
+
+        let old = self.emulator.memory[addr..end].to_vec();
+        self.emulator.memory[addr..end].copy_from_slice(&bytes);
+        for (at, &byte) in (addr..).zip(&bytes) {
+            self.patched.insert(at, byte);
+            self.recent_writes.insert(at, WRITE_HIGHLIGHT_FRAMES);
+        }
+        self.undo.push(Edit::Patch(addr, old.clone()));
+
+        let hex = |bytes: &[u8]| bytes.iter().map(|byte| format!("{:02X}", byte)).collect::<String>();
+        Ok(format!("Patched 0x{:04X}: {} (was {})", addr, hex(&bytes), hex(&old)))
+    }
+
+    fn is_patched(&self, addr: usize) -> bool {
+        self.patched
+            .get(&addr)
+            .is_some_and(|&value| self.emulator.memory.get(addr) == Some(&value))
+    }
+
+    /// Writes the program as it is in memory, patches included, to a ROM
+    /// file. Without a path it goes next to the original as `NAME-patched.ch8`.
+    fn export_rom(&self, path: &str) -> Result<String, String> {
+        let path = if path.is_empty() {
+            let stem = self.rom_path.file_stem().unwrap_or_default().to_string_lossy();
+            self.rom_path.with_file_name(format!("{}-patched.ch8", stem))
+        } else {
+            PathBuf::from(path)
+        };
+
+        let start = 0x200;
+        let patched_end = self
+            .patched
+            .keys()
+            .filter(|&&addr| self.is_patched(addr))
+            .map(|&addr| addr + 1)
+            .max()
+            .unwrap_or(0);
+        let end = (start + self.emulator.rom.len()).max(patched_end).min(self.emulator.memory.len());
+        fs::write(&path, &self.emulator.memory[start..end]).map_err(|err| format!("{}: {}", path.display(), err))?;
+        Ok(format!("Wrote {} bytes to {}", end - start, path.display()))
+    }
+
+    /// Pauses in Inspector Mode after the emulator stopped at a breakpoint
+    /// or watchpoint.
+    fn enter_stop(&mut self, reason: StopReason) {
+        self.emulator.paused = true;
+        self.mode = UiMode::Inspector;
+        self.disasm_view = None;
+        self.stop = Some(reason);
+    }
+
+    fn toggle_breakpoint_at_cursor(&mut self) {
+        let addr = self.disasm_view().cursor as u16;
+        if self.emulator.toggle_breakpoint(addr) {
+            self.set_message(format!("Breakpoint set at 0x{:04X}", addr));
+        } else {
+            self.set_message(format!("Breakpoint cleared at 0x{:04X}", addr));
+        }
+    }
+
+    /// The disassembly panel's current view, following PC with a few
+    /// instructions of context above it unless the panel was scrolled.
+    fn disasm_view(&self) -> DisasmView {
+        let pc = self.emulator.pc;
+        match self.disasm_view {
+            Some(view) if view.pc == pc => view,
+            _ => {
+                let mut top = pc as usize;
+                for _ in 0..DISASM_CONTEXT {
+                    match disassembler::instruction_before(&self.emulator.memory, top) {
+                        Some(addr) => top = addr,
+                        None => break,
+                    }
+                }
+                DisasmView { pc, top, cursor: pc as usize }
+            }
+        }
+    }
+
+    fn set_disasm_view(&mut self, top: usize, cursor: usize) {
+        let pc = self.emulator.pc;
+        self.disasm_view = Some(DisasmView { pc, top, cursor });
+    }
+
+    /// Moves the disassembly cursor by `rows` instructions, scrolling the
+    /// panel when it reaches the top or bottom.
+    fn move_disasm_cursor(&mut self, rows: isize) {
+        let view = self.disasm_view();
+        let memory = &self.emulator.memory;
+        let mut listed = disassembly_addresses(memory, view.top);
+        let mut top = view.top;
+        let mut index = listed.iter().position(|&addr| addr == view.cursor).unwrap_or(0) as isize;
+
+        index += rows;
+        while index < 0 {
+            match disassembler::instruction_before(memory, top) {
+                Some(addr) => top = addr,
+                None => {
+                    index = 0;
+                    break;
+                }
+            }
+            index += 1;
+        }
+        listed = disassembly_addresses(memory, top);
+        while index as usize >= listed.len() && listed.len() > 1 {
+            top = listed[1];
+            index -= 1;
+            listed = disassembly_addresses(memory, top);
+        }
+
+        let cursor = listed.get(index as usize).copied().unwrap_or(top);
+        self.set_disasm_view(top, cursor);
+    }
+
+    /// Shows the target of the jump or call under the disassembly cursor,
+    /// or the memory that an `LD I` under it points at.
+    fn follow_disasm_cursor(&mut self) {
+        let view = self.disasm_view();
+        let memory = &self.emulator.memory;
+        let word = |at: usize| Some((*memory.get(at)? as u16) << 8 | *memory.get(at + 1)? as u16);
+        let Some(opcode) = word(view.cursor) else {
+            return;
+        };
+
+        let target = match opcode & 0xF000 {
+            0x1000 | 0x2000 => opcode & 0x0FFF,
+            0xB000 => {
+                let reg = if self.emulator.quirks.jump_vx { (opcode >> 8) & 0xF } else { 0 };
+                (opcode & 0x0FFF) + self.emulator.v[reg as usize] as u16
+            }
+            0xA000 => {
+                self.memory_cursor = (opcode & 0x0FFF) as usize;
+                self.show_memory = true;
+                return;
+            }
+            _ if opcode == 0xF000 => {
+                // XO-CHIP's long load runs on every platform, so it can point
+                // past the end of a 4 KiB memory.
+                let addr = word(view.cursor + 2).unwrap_or(0);
+                if addr as usize >= memory.len() {
+                    self.set_message(format!("0x{:04X} is outside memory", addr));
+                    return;
+                }
+                self.memory_cursor = addr as usize;
+                self.show_memory = true;
+                return;
+            }
+            _ => {
+                self.set_message("Not a jump, call or LD I");
+                return;
+            }
+        };
+
+        self.disasm_trail.push(view);
+        self.set_disasm_view(target as usize, target as usize);
+        self.set_message(format!("Showing {}", self.describe_address(target)));
+    }
+
+    /// Returns to the view left by the last followed jump or call.
+    fn disasm_back(&mut self) {
+        match self.disasm_trail.pop() {
+            Some(view) => self.set_disasm_view(view.top, view.cursor),
+            None => self.disasm_view = None,
+        }
+    }
+
+    fn is_rewinding(&self) -> bool {
+        self.rewinding_until
+            .is_some_and(|until| Instant::now() < until)
+    }
+
+    /// Restores the previous frame from the rewind buffer.
+    fn rewind_frame(&mut self) {
+        let restored = match self.rewind.step_back() {
+            Some(state) => savestate::decode(&mut self.emulator, state).is_ok(),
+            None => false,
+        };
+
+        if restored {
+            self.fault = None;
+            self.stop = None;
+            self.emulator.update_ghost();
+            self.set_message(format!("<< Rewinding ({} frames left)", self.rewind.len()));
+        } else {
+            self.set_message("Rewind buffer is empty");
+        }
+    }
+
+    fn set_message(&mut self, message: impl Into<String>) {
+        self.message = Some((message.into(), Instant::now()));
+    }
+
+    fn current_message(&self) -> Option<&str> {
+        self.message
+            .as_ref()
+            .filter(|(_, shown_at)| shown_at.elapsed() < MESSAGE_TIMEOUT)
+            .map(|(message, _)| message.as_str())
+    }
+
+    /// The ROM's title, authors and key hints from the ROM database, e.g.
+    /// `Pong by Paul Vervalin | 1 up, Q down`.
+    fn rom_status(&self) -> Option<String> {
+        let info = self.info.as_ref()?;
+        let mut status = info.title.clone();
+        if !info.authors.is_empty() {
+            status.push_str(&format!(" by {}", info.authors.join(", ")));
+        }
+        let keys: Vec<String> = info
+            .keys
+            .iter()
+            .filter_map(|(action, key)| {
+                let (code, _) = KEY_MAP.iter().find(|&&(_, chip8_key)| chip8_key == *key as usize)?;
+                Some(format!("{} {}", code.to_ascii_uppercase(), action))
+            })
+            .collect();
+        if !keys.is_empty() {
+            status.push_str(&format!(" | {}", keys.join(", ")));
+        }
+        Some(status)
+    }
+
+    fn save_state(&mut self) {
+        let path = savestate::slot_path(&self.rom_path, self.slot);
+        match savestate::save(&self.emulator, &path) {
+            Ok(()) => self.set_message(format!("Saved state to slot {}", self.slot)),
+            Err(err) => self.set_message(format!("Save to slot {} failed: {}", self.slot, err)),
+        }
+    }
+
+    fn load_state(&mut self) {
+        let path = savestate::slot_path(&self.rom_path, self.slot);
+        if !path.exists() {
+            self.set_message(format!("Slot {} is empty", self.slot));
+            return;
+        }
+        match savestate::load(&mut self.emulator, &path) {
+            Ok(()) => {
+                self.fault = None;
+                self.stop = None;
+                self.forget_edits();
+                self.move_memory_cursor(0);
+                self.set_message(format!("Loaded state from slot {}", self.slot));
+            }
+            Err(err) => self.set_message(format!("Load from slot {} failed: {}", self.slot, err)),
+        }
+    }
+
+    fn change_slot(&mut self, forward: bool) {
+        self.slot = if forward {
+            self.slot % SLOT_COUNT + 1
+        } else {
+            (self.slot + SLOT_COUNT - 2) % SLOT_COUNT + 1
+        };
+        self.set_message(format!("Save slot {}", self.slot));
+    }
+
+    /// Stops execution and drops into Inspector Mode so the faulting
+    /// instruction can be examined.
+    fn enter_fault(&mut self, fault: Fault) {
+        self.fault = Some(fault);
+        self.emulator.paused = true;
+        self.mode = UiMode::Inspector;
+    }
+}
+
+pub fn run(
+    emulator: Emulator,
+    rom_path: &Path,
+    trace: Option<Trace>,
+    symbols: Symbols,
+    info: Option<RomInfo>,
+    watch: Option<Watch>,
+) -> Result<(), io::Error> {
   
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -32,11 +978,9 @@ pub fn run(mut emulator: Emulator, cycles_per_frame: u32) -> Result<(), io::Erro
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let mut mode = UiMode::Playing;
-    let mut last_timer_update = Instant::now();
-    let timer_interval = Duration::from_millis(1000 / 60); 
+    let mut app = App::new(emulator, rom_path, trace, symbols, info, watch);
 
-    let result = run_app(&mut terminal, &mut emulator, cycles_per_frame, &mut mode, &mut last_timer_update, timer_interval);
+    let result = run_app(&mut terminal, &mut app);
 
   
     disable_raw_mode()?;
@@ -48,47 +992,51 @@ pub fn run(mut emulator: Emulator, cycles_per_frame: u32) -> Result<(), io::Erro
 
 fn run_app(
     terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
-    emulator: &mut Emulator,
-    cycles_per_frame: u32,
-    mode: &mut UiMode,
-    last_timer_update: &mut Instant,
-    timer_interval: Duration,
+    app: &mut App,
 ) -> Result<(), io::Error> {
     let mut keys_pressed = Vec::new();
     
     loop {
+        app.check_watched_rom();
 
-        emulator.keypad = [false; 16];
+        app.emulator.keypad = [false; 16];
         
         keys_pressed.clear();
         while event::poll(Duration::from_millis(0))? {
             if let Event::Key(key) = event::read()? {
-                if handle_input(emulator, key, mode, &mut keys_pressed) {
+                if handle_input(app, key, &mut keys_pressed) {
                     return Ok(()); 
                 }
             }
         }
         
+        let mut keypad = app.forced_keys;
         for key_idx in &keys_pressed {
-            emulator.keypad[*key_idx] = true;
+            keypad[*key_idx] = true;
         }
+        app.emulator.keypad = keypad;
 
-        if last_timer_update.elapsed() >= timer_interval {
-            emulator.update_timers();
-            *last_timer_update = Instant::now();
-        }
-
-        if !emulator.paused {
-            for _ in 0..cycles_per_frame {
-                emulator.cycle();
+        if !app.emulator.paused {
+            if app.is_rewinding() {
+                app.rewind_frame();
+            } else {
+                match app.run_frame(keypad) {
+                    Ok(output) => {
+                        app.rewind.push(savestate::encode(&app.emulator));
+                        if let Some(reason) = output.stop {
+                            app.enter_stop(reason);
+                        }
+                    }
+                    Err(fault) => app.enter_fault(fault),
+                }
+                app.note_writes();
             }
-            emulator.update_ghost();
         }
 
         terminal.draw(|f| {
-            match mode {
-                UiMode::Playing => render_playing(f, emulator),
-                UiMode::Inspector => render_inspector(f, emulator),
+            match app.mode {
+                UiMode::Playing => render_playing(f, app),
+                UiMode::Inspector => render_inspector(f, app),
             }
         })?;
 
@@ -96,42 +1044,199 @@ fn run_app(
     }
 }
 
-fn handle_input(emulator: &mut Emulator, key: KeyEvent, mode: &mut UiMode, keys_pressed: &mut Vec<usize>) -> bool {
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+fn handle_input(app: &mut App, key: KeyEvent, keys_pressed: &mut Vec<usize>) -> bool {
+    if app.prompt.is_some() {
+        handle_prompt_input(app, key);
+        return false;
+    }
+
+    if app.memory_edit.is_some() {
+        app.handle_memory_edit(key);
+        return false;
+    }
+
+    if app.cpu_edit.is_some() {
+        app.handle_cpu_edit(key);
+        return false;
+    }
+
+    if matches!(app.mode, UiMode::Inspector) && key.code == KeyCode::Char(':') {
+        app.prompt = Some(String::new());
+        app.prompt_error = None;
+        return false;
+    }
+
+    if matches!(app.mode, UiMode::Inspector) {
+        if app.cpu_cursor.is_some() {
+            if handle_cpu_keys(app, key) {
+                return false;
+            }
+        } else if app.show_memory && handle_memory_keys(app, key) {
+            return false;
+        }
+    }
+
+    let emulator = &mut app.emulator;
  
     match key.code {
         KeyCode::Esc => return true, 
         KeyCode::Char('i') | KeyCode::Char('I') => {
-            *mode = match mode {
+            app.mode = match app.mode {
                 UiMode::Playing => {
                     emulator.paused = true;
                     UiMode::Inspector
                 }
                 UiMode::Inspector => {
                     emulator.paused = false;
+                    emulator.resume();
                     UiMode::Playing
                 }
             };
             return false;
         }
-        KeyCode::Char('p') | KeyCode::Char('P') => {
-            emulator.paused = !emulator.paused;
+        KeyCode::Char('p') | KeyCode::Char('P') => {
+            emulator.paused = !emulator.paused;
+            emulator.run_until = None;
+            emulator.resume();
+            return false;
+        }
+        KeyCode::Backspace => {
+            // While paused each press steps back a single frame; while
+            // running, holding the key scrubs backwards continuously.
+            if emulator.paused {
+                app.rewind_frame();
+            } else {
+                app.rewinding_until = Some(Instant::now() + REWIND_HOLD);
+            }
+            return false;
+        }
+        KeyCode::F(5) => {
+            app.save_state();
+            return false;
+        }
+        KeyCode::F(9) => {
+            app.load_state();
+            return false;
+        }
+        KeyCode::F(6) => {
+            app.change_slot(false);
+            return false;
+        }
+        KeyCode::F(7) => {
+            app.change_slot(true);
             return false;
         }
         _ => {}
     }
 
-    if matches!(mode, UiMode::Inspector) {
+    // While the program runs, keys on the keypad go to it, so the inspector
+    // commands sharing them (S, C, R, A, F, Z, X) only work while paused.
+    let keypad_key = match key.code {
+        KeyCode::Char(c) => KEY_MAP.iter().any(|&(code, _)| code == c.to_ascii_lowercase()),
+        _ => false,
+    };
+    if matches!(app.mode, UiMode::Inspector) && (emulator.paused || !keypad_key) {
         match key.code {
             KeyCode::Char('s') | KeyCode::Char('S') => {
-                emulator.cycle();
+                app.step();
+                return false;
+            }
+            KeyCode::Char('m') | KeyCode::Char('M') => {
+                app.show_memory = !app.show_memory;
+                return false;
+            }
+            KeyCode::Tab => {
+                app.cpu_cursor = match app.cpu_cursor {
+                    Some(_) => None,
+                    None => Some(0),
+                };
+                return false;
+            }
+            KeyCode::Char('u') | KeyCode::Char('U') => {
+                app.undo_edit();
+                return false;
+            }
+            KeyCode::Up => {
+                app.move_disasm_cursor(-1);
+                return false;
+            }
+            KeyCode::Down => {
+                app.move_disasm_cursor(1);
+                return false;
+            }
+            KeyCode::PageUp => {
+                app.move_disasm_cursor(-(DISASM_ROWS as isize));
+                return false;
+            }
+            KeyCode::PageDown => {
+                app.move_disasm_cursor(DISASM_ROWS as isize);
+                return false;
+            }
+            KeyCode::Enter | KeyCode::Right => {
+                app.follow_disasm_cursor();
+                return false;
+            }
+            KeyCode::Left => {
+                app.disasm_back();
+                return false;
+            }
+            KeyCode::Home => {
+                app.disasm_view = None;
+                app.disasm_trail.clear();
+                return false;
+            }
+            KeyCode::Char('b') | KeyCode::Char('B') => {
+                app.toggle_breakpoint_at_cursor();
+                return false;
+            }
+            KeyCode::Char('a') | KeyCode::Char('A') => {
+                // Start from the current instruction, ready to be edited.
+                let (text, _) = app.disassemble(app.disasm_view().cursor);
+                let text: Vec<&str> = text.split_whitespace().collect();
+                app.prompt = Some(format!("a {}", text.join(" ")));
+                app.prompt_error = None;
                 return false;
             }
             KeyCode::Char('c') | KeyCode::Char('C') => {
                 emulator.paused = false;
+                emulator.run_until = None;
+                emulator.resume();
+                app.stop = None;
+                return false;
+            }
+            KeyCode::Char('z') | KeyCode::Char('Z') => {
+                app.step_back();
+                return false;
+            }
+            KeyCode::Char('x') | KeyCode::Char('X') => {
+                app.reverse_continue();
+                return false;
+            }
+            KeyCode::Char('o') | KeyCode::Char('O') if emulator.paused => {
+                app.step_over();
+                return false;
+            }
+            KeyCode::Char('t') | KeyCode::Char('T') if emulator.paused => {
+                app.step_out();
+                return false;
+            }
+            KeyCode::Char('h') | KeyCode::Char('H') if emulator.paused => {
+                app.run_to_cursor();
+                return false;
+            }
+            KeyCode::Char('f') | KeyCode::Char('F') => {
+                app.advance_frame();
                 return false;
             }
             KeyCode::Char('r') | KeyCode::Char('R') => {
                 emulator.reset();
+                app.fault = None;
+                app.stop = None;
+                app.forget_edits();
                 return false;
             }
             KeyCode::Char('k') | KeyCode::Char('K') => {
@@ -140,39 +1245,17 @@ fn handle_input(emulator: &mut Emulator, key: KeyEvent, mode: &mut UiMode, keys_
             }
             KeyCode::Char('l') | KeyCode::Char('L') => {
                 emulator.load_checkpoint();
+                app.fault = None;
+                app.stop = None;
+                app.forget_edits();
                 return false;
             }
             _ => {}
         }
     }
 
-    // CHIP-8 keypad mapping (0-F)
-    // Original:  1 2 3 C    Keyboard:  1 2 3 4
-    //            4 5 6 D               Q W E R
-    //            7 8 9 E               A S D F
-    //            A 0 B F               Z X C V
-    let key_map = [
-        (KeyCode::Char('1'), 0x1),
-        (KeyCode::Char('2'), 0x2),
-        (KeyCode::Char('3'), 0x3),
-        (KeyCode::Char('4'), 0xC),
-        (KeyCode::Char('q'), 0x4),
-        (KeyCode::Char('w'), 0x5),
-        (KeyCode::Char('e'), 0x6),
-        (KeyCode::Char('r'), 0xD),
-        (KeyCode::Char('a'), 0x7),
-        (KeyCode::Char('s'), 0x8),
-        (KeyCode::Char('d'), 0x9),
-        (KeyCode::Char('f'), 0xE),
-        (KeyCode::Char('z'), 0xA),
-        (KeyCode::Char('x'), 0x0),
-        (KeyCode::Char('c'), 0xB),
-        (KeyCode::Char('v'), 0xF),
-    ];
-
-
-    for (code, chip8_key) in key_map.iter() {
-        if key.code == *code {
+    for (code, chip8_key) in KEY_MAP.iter() {
+        if key.code == KeyCode::Char(*code) {
             if !keys_pressed.contains(chip8_key) {
                 keys_pressed.push(*chip8_key);
             }
@@ -183,14 +1266,253 @@ fn handle_input(emulator: &mut Emulator, key: KeyEvent, mode: &mut UiMode, keys_
     false
 }
 
-fn render_playing(f: &mut Frame, emulator: &Emulator) {
+/// Navigation and editing keys for the CPU panel while it has focus.
+/// Returns whether the key was used.
+fn handle_cpu_keys(app: &mut App, key: KeyEvent) -> bool {
+    let count = CpuField::all().len();
+    let Some(cursor) = app.cpu_cursor else {
+        return false;
+    };
+
+    match key.code {
+        KeyCode::Up | KeyCode::Left => app.cpu_cursor = Some((cursor + count - 1) % count),
+        KeyCode::Down | KeyCode::Right => app.cpu_cursor = Some((cursor + 1) % count),
+        KeyCode::Enter => {
+            if !app.emulator.paused {
+                app.set_message("Pause execution to edit registers");
+            } else if let Some(field @ CpuField::Key(_)) = app.selected_cpu_field() {
+                let held = app.cpu_value(field);
+                app.edit_cpu(field, held ^ 1);
+            } else {
+                app.cpu_edit = Some(String::new());
+            }
+        }
+        _ => return false,
+    }
+    true
+}
+
+/// Navigation and editing keys for the memory panel. Returns whether the key
+/// was used.
+fn handle_memory_keys(app: &mut App, key: KeyEvent) -> bool {
+    let row = MEMORY_ROW as isize;
+    match key.code {
+        KeyCode::Up => app.move_memory_cursor(-row),
+        KeyCode::Down => app.move_memory_cursor(row),
+        KeyCode::Left => app.move_memory_cursor(-1),
+        KeyCode::Right => app.move_memory_cursor(1),
+        KeyCode::PageUp => app.move_memory_cursor(-row * 16),
+        KeyCode::PageDown => app.move_memory_cursor(row * 16),
+        KeyCode::Char('g') | KeyCode::Char('G') => {
+            app.prompt = Some("g ".to_string());
+            app.prompt_error = None;
+        }
+        KeyCode::Enter => {
+            if app.emulator.paused {
+                app.memory_edit = Some(String::new());
+            } else {
+                app.set_message("Pause execution to edit memory");
+            }
+        }
+        _ => return false,
+    }
+    true
+}
+
+fn handle_prompt_input(app: &mut App, key: KeyEvent) {
+    let Some(input) = app.prompt.as_mut() else {
+        return;
+    };
+    app.prompt_error = None;
+
+    match key.code {
+        KeyCode::Esc => app.prompt = None,
+        KeyCode::Backspace => {
+            input.pop();
+        }
+        KeyCode::Char(c) => input.push(c),
+        KeyCode::Enter => {
+            let command = input.trim().to_string();
+            match run_command(app, &command) {
+                Ok(message) => {
+                    app.prompt = None;
+                    app.set_message(message);
+                }
+                Err(error) => app.prompt_error = Some(error),
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Runs a command typed at the inspector's command line, returning a status
+/// message on success or an error to show next to the command.
+///
+/// ```text
+/// b ADDR [if COND] [hits N]   set a breakpoint
+/// d ADDR | d all              delete breakpoints
+/// w START[..END] [rwx]        watch memory (default: writes)
+/// dw ADDR | dw all            delete watchpoints covering ADDR
+/// g ADDR                      show memory at ADDR
+/// a INSTR                     assemble INSTR at the disassembly cursor
+/// export [FILE]               write memory out as a ROM
+/// s [N]                       step N instructions
+/// until ADDR                  run until PC reaches ADDR
+/// p EXPR                      evaluate an expression
+/// ```
+fn run_command(app: &mut App, command: &str) -> Result<String, String> {
+    let (name, rest) = command.split_once(char::is_whitespace).unwrap_or((command, ""));
+    let rest = rest.trim();
+
+    match name {
+        "" => Ok(String::new()),
+        "b" | "break" => {
+            let (rest, hit_target) = match rest.rsplit_once(" hits ") {
+                Some((before, count)) => {
+                    let count = count.trim().parse::<u32>().map_err(|_| format!("invalid hit count '{}'", count.trim()))?;
+                    (before, Some(count))
+                }
+                None => (rest, None),
+            };
+            let (address, condition) = match rest.split_once(" if ") {
+                Some((address, condition)) => (address, Some(condition)),
+                None => (rest, None),
+            };
+
+            let addr = eval_address(app, address)?;
+            let condition = condition
+                .map(Condition::parse)
+                .transpose()
+                .map_err(|err| format!("condition: {}", err))?;
+
+            let name = app.emulator.breakpoints.get(&addr).and_then(|existing| existing.name.clone());
+            let breakpoint = Breakpoint {
+                name,
+                condition,
+                hit_target,
+                hits: 0,
+            };
+            let description = breakpoint.describe();
+            app.emulator.breakpoints.insert(addr, breakpoint);
+
+            if description.is_empty() {
+                Ok(format!("Breakpoint set at 0x{:04X}", addr))
+            } else {
+                Ok(format!("Breakpoint set at 0x{:04X} {}", addr, description))
+            }
+        }
+        "d" | "delete" => {
+            if rest == "all" {
+                app.emulator.breakpoints.clear();
+                return Ok("All breakpoints deleted".to_string());
+            }
+            let addr = eval_address(app, rest)?;
+            match app.emulator.breakpoints.remove(&addr) {
+                Some(_) => Ok(format!("Breakpoint deleted at 0x{:04X}", addr)),
+                None => Err(format!("no breakpoint at 0x{:04X}", addr)),
+            }
+        }
+        "w" | "watch" => {
+            let (range, mode) = match rest.rsplit_once(char::is_whitespace) {
+                Some((range, mode)) if mode.chars().all(|c| "rwx".contains(c)) => (range, mode),
+                _ => (rest, "w"),
+            };
+            let (start, end) = match range.split_once("..") {
+                Some((start, end)) => (eval_address(app, start)?, eval_address(app, end)?),
+                None => {
+                    let addr = eval_address(app, range)?;
+                    (addr, addr)
+                }
+            };
+            if end < start {
+                return Err(format!("range 0x{:04X}..0x{:04X} is backwards", start, end));
+            }
+
+            let watchpoint = Watchpoint {
+                start,
+                end,
+                read: mode.contains('r'),
+                write: mode.contains('w'),
+                execute: mode.contains('x'),
+            };
+            app.emulator.watchpoints.push(watchpoint);
+            Ok(format!("Watching {}", watchpoint.describe()))
+        }
+        "dw" => {
+            if rest == "all" {
+                app.emulator.watchpoints.clear();
+                return Ok("All watchpoints deleted".to_string());
+            }
+            let addr = eval_address(app, rest)? as usize;
+            let before = app.emulator.watchpoints.len();
+            app.emulator.watchpoints.retain(|w| !w.contains(addr));
+            match before - app.emulator.watchpoints.len() {
+                0 => Err(format!("no watchpoint covers 0x{:04X}", addr)),
+                1 => Ok("Watchpoint deleted".to_string()),
+                n => Ok(format!("{} watchpoints deleted", n)),
+            }
+        }
+        "s" | "step" => {
+            let count = if rest.is_empty() {
+                1
+            } else {
+                rest.parse::<u32>().map_err(|_| format!("invalid step count '{}'", rest))?
+            };
+            let executed = app.step_count(count);
+            Ok(format!("Stepped {} instruction(s), now at 0x{:04X}", executed, app.emulator.pc))
+        }
+        "until" => {
+            let addr = eval_address(app, rest)?;
+            app.run_until(RunUntil::Address(addr));
+            Ok(format!("Running to 0x{:04X}", addr))
+        }
+        "a" | "asm" => app.patch_instruction(rest),
+        "export" => app.export_rom(rest),
+        "g" | "goto" => {
+            let addr = eval_address(app, rest)?;
+            app.memory_cursor = addr as usize;
+            app.show_memory = true;
+            Ok(format!("Memory at 0x{:04X}", addr))
+        }
+        "p" | "print" => {
+            let expr = Expr::parse(rest).map_err(|err| err.to_string())?;
+            let value = expr.eval(&app.emulator);
+            Ok(format!("{} = {} (0x{:X})", rest, value, value))
+        }
+        _ => Err(format!("unknown command '{}'", name)),
+    }
+}
+
+/// Evaluates an address typed as a label or an expression, e.g. `main`,
+/// `0x2A4` or `PC+4`.
+fn eval_address(app: &App, source: &str) -> Result<u16, String> {
+    let emulator = &app.emulator;
+    if source.trim().is_empty() {
+        return Err("expected an address".to_string());
+    }
+    if let Some(addr) = app.symbols.address(source.trim()) {
+        return Ok(addr);
+    }
+    let value = Expr::parse(source).map_err(|err| format!("address: {}", err))?.eval(emulator);
+    u16::try_from(value)
+        .ok()
+        .filter(|&addr| (addr as usize) < emulator.memory.len())
+        .ok_or_else(|| format!("address 0x{:X} is outside memory", value))
+}
+
+fn render_playing(f: &mut Frame, app: &App) {
+    let emulator = &app.emulator;
     let size = f.area();
 
 
     let display_area = centered_rect(70, 80, size);
 
+    let title = match &app.info {
+        Some(info) => format!(" {} ", info.title),
+        None => " CHIP-8 Emulator ".to_string(),
+    };
     let block = Block::default()
-        .title(" CHIP-8 Emulator ")
+        .title(title)
         .borders(Borders::ALL)
         .border_style(Style::default().fg(Color::Cyan));
 
@@ -198,7 +1520,7 @@ fn render_playing(f: &mut Frame, emulator: &Emulator) {
     f.render_widget(block, display_area);
 
 
-    let display_text = render_display_crt(&emulator.display, &emulator.ghost_display);
+    let display_text = render_display_crt(emulator, &app.palette);
     let display_widget = Paragraph::new(display_text)
         .style(Style::default().fg(Color::Green));
 
@@ -212,10 +1534,20 @@ fn render_playing(f: &mut Frame, emulator: &Emulator) {
         height: 1,
     };
 
-    let status = if emulator.paused {
-        " [PAUSED] Press I for Inspector | P to Resume | ESC to Quit "
+    let status = if let Some(message) = app.current_message() {
+        format!(" {} ", message)
     } else {
-        " Press I for Inspector | P to Pause | ESC to Quit "
+        let help = if emulator.exited {
+            "[EXITED] Press I for Inspector | R in Inspector to Reset | ESC to Quit"
+        } else if emulator.paused {
+            "[PAUSED] Press I for Inspector | P to Resume | ESC to Quit"
+        } else {
+            "Press I for Inspector | P to Pause | Hold Backspace to Rewind | ESC to Quit"
+        };
+        match app.rom_status() {
+            Some(rom) => format!(" {} | {} ", rom, help),
+            None => format!(" {} ", help),
+        }
     };
 
     let status_widget = Paragraph::new(status)
@@ -226,7 +1558,8 @@ fn render_playing(f: &mut Frame, emulator: &Emulator) {
     }
 }
 
-fn render_inspector(f: &mut Frame, emulator: &Emulator) {
+fn render_inspector(f: &mut Frame, app: &App) {
+    let emulator = &app.emulator;
     let size = f.area();
 
 
@@ -234,7 +1567,8 @@ fn render_inspector(f: &mut Frame, emulator: &Emulator) {
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Percentage(70),
-            Constraint::Percentage(30),
+            Constraint::Min(0),
+            Constraint::Length(1),
         ])
         .split(size);
 
@@ -257,70 +1591,161 @@ fn render_inspector(f: &mut Frame, emulator: &Emulator) {
     let cpu_chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Percentage(70),
-            Constraint::Percentage(30),
+            Constraint::Percentage(55),
+            Constraint::Percentage(20),
+            Constraint::Percentage(25),
         ])
         .split(top_chunks[1]);
 
-    render_display_widget(f, emulator, top_chunks[0]);
+    if app.show_memory {
+        render_memory(f, app, top_chunks[0]);
+    } else {
+        render_display_widget(f, emulator, &app.palette, top_chunks[0]);
+    }
+
+    render_cpu_state(f, app, cpu_chunks[0]);
 
-    render_cpu_state(f, emulator, cpu_chunks[0]);
+    render_stack(f, app, cpu_chunks[1]);
 
-    render_stack(f, emulator, cpu_chunks[1]);
+    render_breakpoints(f, app, cpu_chunks[2]);
 
-    render_disassembly(f, emulator, bottom_chunks[0]);
+    render_disassembly(f, app, bottom_chunks[0]);
 
-    render_controls(f, emulator, bottom_chunks[1]);
+    render_controls(f, app, bottom_chunks[1]);
+
+    render_command_line(f, app, main_chunks[2]);
 }
 
-fn render_display_widget(f: &mut Frame, emulator: &Emulator, area: Rect) {
+fn render_display_widget(f: &mut Frame, emulator: &Emulator, palette: &[Color; 16], area: Rect) {
+    let title = format!(" Display {}x{} ", emulator.width(), emulator.height());
     let block = Block::default()
-        .title(" Display ")
+        .title(title)
         .borders(Borders::ALL)
         .border_style(Style::default().fg(Color::Cyan));
 
     let inner = block.inner(area);
     f.render_widget(block, area);
 
-    let display_text = render_display_crt(&emulator.display, &emulator.ghost_display);
+    let display_text = render_display_crt(emulator, palette);
     let display_widget = Paragraph::new(display_text)
         .style(Style::default().fg(Color::Green));
 
     f.render_widget(display_widget, inner);
 }
 
-fn render_cpu_state(f: &mut Frame, emulator: &Emulator, area: Rect) {
+fn render_memory(f: &mut Frame, app: &App, area: Rect) {
+    let emulator = &app.emulator;
+    let title = if app.memory_edit.is_some() {
+        format!(" Memory 0x{:04X} - type hex, Enter/Esc to finish ", app.memory_cursor)
+    } else {
+        format!(" Memory 0x{:04X} - arrows/PgUp/PgDn move, Enter edit, G goto ", app.memory_cursor)
+    };
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    // Keep the cursor's row in the middle of the panel where possible.
+    let rows = (inner.height as usize).max(1);
+    let total_rows = emulator.memory.len().div_ceil(MEMORY_ROW);
+    let cursor_row = app.memory_cursor / MEMORY_ROW;
+    let first_row = cursor_row
+        .saturating_sub(rows / 2)
+        .min(total_rows.saturating_sub(rows));
+
+    let pc = emulator.pc as usize;
+    let mut lines = vec![];
+
+    for row in first_row..(first_row + rows).min(total_rows) {
+        let start = row * MEMORY_ROW;
+        let bytes = &emulator.memory[start..(start + MEMORY_ROW).min(emulator.memory.len())];
+
+        let mut spans = vec![Span::styled(format!("{:04X} ", start), Style::default().fg(Color::DarkGray))];
+        let mut ascii = String::with_capacity(MEMORY_ROW);
+
+        for (offset, &byte) in bytes.iter().enumerate() {
+            let addr = start + offset;
+
+            let mut style = Style::default();
+            if app.is_patched(addr) {
+                style = style.fg(Color::Magenta).add_modifier(Modifier::BOLD);
+            }
+            if app.recent_writes.contains_key(&addr) {
+                style = style.fg(Color::Red).add_modifier(Modifier::BOLD);
+            }
+            if addr == emulator.i as usize {
+                style = style.fg(Color::Cyan).add_modifier(Modifier::UNDERLINED);
+            }
+            if addr == pc || addr == pc + 1 {
+                style = style.fg(Color::Yellow).add_modifier(Modifier::BOLD);
+            }
+
+            let text = match &app.memory_edit {
+                Some(digits) if addr == app.memory_cursor => format!("{:_<2}", digits),
+                _ => format!("{:02X}", byte),
+            };
+            if addr == app.memory_cursor {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+
+            spans.push(Span::raw(" "));
+            spans.push(Span::styled(text, style));
+            ascii.push(if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' });
+        }
+
+        spans.push(Span::raw("  "));
+        spans.push(Span::styled(ascii, Style::default().fg(Color::DarkGray)));
+        lines.push(Line::from(spans));
+    }
+
+    f.render_widget(Paragraph::new(lines), inner);
+}
+
+fn render_cpu_state(f: &mut Frame, app: &App, area: Rect) {
+    let emulator = &app.emulator;
+    let title = if app.cpu_cursor.is_some() {
+        " CPU State - arrows select, Enter edit, U undo, Tab leave "
+    } else {
+        " CPU State "
+    };
     let block = Block::default()
-        .title(" CPU State ")
+        .title(title)
         .borders(Borders::ALL)
         .border_style(Style::default().fg(Color::Yellow));
 
     let inner = block.inner(area);
     f.render_widget(block, area);
 
+    let selected = app.selected_cpu_field();
+    let value = |field: CpuField, text: String| {
+        if selected != Some(field) {
+            return Span::raw(text);
+        }
+        let text = match &app.cpu_edit {
+            Some(digits) => format!("{:_<width$}", digits, width = text.len()),
+            None => text,
+        };
+        Span::styled(text, Style::default().add_modifier(Modifier::REVERSED))
+    };
+
     let mut lines = vec![];
 
     lines.push(Line::from(vec![
         Span::styled("PC: ", Style::default().fg(Color::Cyan)),
-        Span::raw(format!("0x{:04X}", emulator.pc)),
-    ]));
-    lines.push(Line::from(vec![
-        Span::styled("I:  ", Style::default().fg(Color::Cyan)),
-        Span::raw(format!("0x{:04X}", emulator.i)),
+        value(CpuField::Pc, format!("0x{:04X}", emulator.pc)),
+        Span::styled("  I:  ", Style::default().fg(Color::Cyan)),
+        value(CpuField::I, format!("0x{:04X}", emulator.i)),
     ]));
     lines.push(Line::from(vec![
         Span::styled("SP: ", Style::default().fg(Color::Cyan)),
-        Span::raw(format!("0x{:02X}", emulator.sp)),
-    ]));
-    lines.push(Line::from(""));
-
-    lines.push(Line::from(vec![
-        Span::styled("DT: ", Style::default().fg(Color::Cyan)),
-        Span::raw(format!("{:3}", emulator.delay_timer)),
-    ]));
-    lines.push(Line::from(vec![
-        Span::styled("ST: ", Style::default().fg(Color::Cyan)),
-        Span::raw(format!("{:3}", emulator.sound_timer)),
+        value(CpuField::Sp, format!("0x{:02X}", emulator.sp)),
+        Span::styled("  DT: ", Style::default().fg(Color::Cyan)),
+        value(CpuField::Dt, format!("0x{:02X}", emulator.delay_timer)),
+        Span::styled("  ST: ", Style::default().fg(Color::Cyan)),
+        value(CpuField::St, format!("0x{:02X}", emulator.sound_timer)),
     ]));
     lines.push(Line::from(""));
 
@@ -329,17 +1754,39 @@ fn render_cpu_state(f: &mut Frame, emulator: &Emulator, area: Rect) {
         let right_reg = i + 8;
         lines.push(Line::from(vec![
             Span::styled(format!("V{:X}: ", left_reg), Style::default().fg(Color::Green)),
-            Span::raw(format!("{:02X}  ", emulator.v[left_reg])),
+            value(CpuField::V(left_reg as u8), format!("{:02X}", emulator.v[left_reg])),
+            Span::raw("  "),
             Span::styled(format!("V{:X}: ", right_reg), Style::default().fg(Color::Green)),
-            Span::raw(format!("{:02X}", emulator.v[right_reg])),
+            value(CpuField::V(right_reg as u8), format!("{:02X}", emulator.v[right_reg])),
         ]));
     }
 
+    lines.push(Line::from(""));
+    let mut keys = vec![Span::styled("Keys: ", Style::default().fg(Color::Cyan))];
+    for key in 0..16u8 {
+        let style = if app.forced_keys[key as usize] {
+            Style::default().fg(Color::Black).bg(Color::Yellow)
+        } else if emulator.keypad[key as usize] {
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        };
+        keys.push(value(CpuField::Key(key), format!("{:X}", key)).patch_style(style));
+        keys.push(Span::raw(" "));
+    }
+    lines.push(Line::from(keys));
+
+    lines.push(Line::from(vec![
+        Span::styled("Seed: ", Style::default().fg(Color::Cyan)),
+        Span::raw(format!("{}", emulator.seed)),
+    ]));
+
     let cpu_widget = Paragraph::new(lines);
     f.render_widget(cpu_widget, inner);
 }
 
-fn render_stack(f: &mut Frame, emulator: &Emulator, area: Rect) {
+fn render_stack(f: &mut Frame, app: &App, area: Rect) {
+    let emulator = &app.emulator;
     let block = Block::default()
         .title(" Stack ")
         .borders(Borders::ALL)
@@ -353,53 +1800,110 @@ fn render_stack(f: &mut Frame, emulator: &Emulator, area: Rect) {
     if emulator.sp == 0 {
         lines.push(Line::from(Span::styled("Empty", Style::default().fg(Color::DarkGray))));
     } else {
-        for i in (0..emulator.sp.min(8)).rev() {
-            let addr = emulator.stack[i as usize];
-            let marker = if i == emulator.sp - 1 { ">" } else { " " };
+        // Newest first; when they do not all fit, the oldest make way for a
+        // count of how many are hidden.
+        let depth = emulator.sp as usize;
+        let rows = inner.height as usize;
+        let shown = if depth > rows { rows.saturating_sub(1) } else { depth };
+        for i in (depth - shown..depth).rev() {
+            let addr = emulator.stack[i];
+            let marker = if i == depth - 1 { ">" } else { " " };
             lines.push(Line::from(vec![
                 Span::raw(marker),
                 Span::styled(format!(" [{:X}] ", i), Style::default().fg(Color::Cyan)),
-                Span::raw(format!("0x{:04X}", addr)),
+                Span::raw(app.describe_address(addr)),
             ]));
         }
+        if shown < depth {
+            lines.push(Line::from(Span::styled(
+                format!("  ... {} more", depth - shown),
+                Style::default().fg(Color::DarkGray),
+            )));
+        }
     }
 
     let stack_widget = Paragraph::new(lines);
     f.render_widget(stack_widget, inner);
 }
 
-fn render_disassembly(f: &mut Frame, emulator: &Emulator, area: Rect) {
+/// Addresses of the instructions listed in the disassembly panel, starting
+/// at `top` and stepping over four-byte XO-CHIP instructions.
+fn disassembly_addresses(memory: &[u8], top: usize) -> Vec<usize> {
+    let mut rows = Vec::with_capacity(DISASM_ROWS);
+    let mut addr = top;
+
+    while rows.len() < DISASM_ROWS && addr + 1 < memory.len() {
+        rows.push(addr);
+        addr += disassembler::disassemble_at(memory, addr).1;
+    }
+
+    rows
+}
+
+/// Addresses that a `JP` or `CALL` in the loaded ROM leads to.
+fn branch_targets(emulator: &Emulator) -> HashSet<usize> {
+    let end = (0x200 + emulator.rom.len()).min(emulator.memory.len());
+    (0x200..end)
+        .step_by(2)
+        .filter_map(|addr| {
+            let opcode = (emulator.memory[addr] as u16) << 8 | *emulator.memory.get(addr + 1)? as u16;
+            matches!(opcode & 0xF000, 0x1000 | 0x2000).then_some((opcode & 0x0FFF) as usize)
+        })
+        .collect()
+}
+
+fn render_disassembly(f: &mut Frame, app: &App, area: Rect) {
+    let emulator = &app.emulator;
+    let view = app.disasm_view();
+    let title = if app.disasm_view.is_some_and(|scrolled| scrolled.pc == emulator.pc) {
+        " Disassembly (Home: back to PC) "
+    } else {
+        " Disassembly "
+    };
     let block = Block::default()
-        .title(" Disassembly ")
+        .title(title)
         .borders(Borders::ALL)
         .border_style(Style::default().fg(Color::Blue));
 
     let inner = block.inner(area);
     f.render_widget(block, area);
 
+    let targets = branch_targets(emulator);
     let mut lines = vec![];
 
-    for i in 0..12 {
-        let addr = emulator.pc + (i * 2);
-        if addr >= 4094 {
-            break;
-        }
-
-        let opcode = (emulator.memory[addr as usize] as u16) << 8
-            | emulator.memory[addr as usize + 1] as u16;
-        let disasm = disassembler::disassemble(opcode);
+    for addr in disassembly_addresses(&emulator.memory, view.top) {
+        let opcode = (emulator.memory[addr] as u16) << 8 | emulator.memory[addr + 1] as u16;
+        let (disasm, len) = app.disassemble(addr);
+        let label = match app.symbols.name(addr as u16) {
+            Some(name) => format!("{}: ", name),
+            None => String::new(),
+        };
 
-        let marker = if i == 0 { ">" } else { " " };
-        let style = if i == 0 {
+        let is_pc = addr == emulator.pc as usize;
+        let breakpoint = if emulator.breakpoints.contains_key(&(addr as u16)) { "●" } else { " " };
+        let target = if targets.contains(&addr) { "◆" } else { " " };
+        let marker = if is_pc { ">" } else { " " };
+        let mut style = if is_pc {
             Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
         } else {
             Style::default()
         };
+        if addr == view.cursor {
+            style = style.add_modifier(Modifier::REVERSED);
+        }
+        let opcode_style = if (addr..addr + len).any(|at| app.is_patched(at)) {
+            Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::Cyan)
+        };
 
         lines.push(Line::from(vec![
+            Span::styled(breakpoint, Style::default().fg(Color::Red)),
+            Span::styled(target, Style::default().fg(Color::Green)),
             Span::raw(marker),
             Span::styled(format!(" {:04X}: ", addr), Style::default().fg(Color::DarkGray)),
-            Span::styled(format!("{:04X} ", opcode), Style::default().fg(Color::Cyan)),
+            Span::styled(format!("{:04X} ", opcode), opcode_style),
+            Span::styled(label, Style::default().fg(Color::Magenta)),
             Span::styled(disasm, style),
         ]));
     }
@@ -408,7 +1912,46 @@ fn render_disassembly(f: &mut Frame, emulator: &Emulator, area: Rect) {
     f.render_widget(disasm_widget, inner);
 }
 
-fn render_controls(f: &mut Frame, emulator: &Emulator, area: Rect) {
+fn render_breakpoints(f: &mut Frame, app: &App, area: Rect) {
+    let emulator = &app.emulator;
+    let block = Block::default()
+        .title(" Breakpoints & Watchpoints ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Red));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let mut lines = vec![];
+
+    if emulator.breakpoints.is_empty() && emulator.watchpoints.is_empty() {
+        lines.push(Line::from(Span::styled("None", Style::default().fg(Color::DarkGray))));
+    }
+
+    for (&addr, breakpoint) in &emulator.breakpoints {
+        let (disasm, _) = app.disassemble(addr as usize);
+        let marker = if addr == emulator.pc { ">" } else { " " };
+        lines.push(Line::from(vec![
+            Span::raw(marker),
+            Span::styled(format!(" {} ", app.describe_address(addr)), Style::default().fg(Color::Cyan)),
+            Span::raw(format!("{:<16} ", disasm)),
+            Span::styled(breakpoint.describe(), Style::default().fg(Color::DarkGray)),
+        ]));
+    }
+
+    for watchpoint in &emulator.watchpoints {
+        lines.push(Line::from(vec![
+            Span::styled("W", Style::default().fg(Color::Magenta)),
+            Span::styled(format!(" {}", watchpoint.describe()), Style::default().fg(Color::Cyan)),
+        ]));
+    }
+
+    let breakpoints_widget = Paragraph::new(lines);
+    f.render_widget(breakpoints_widget, inner);
+}
+
+fn render_controls(f: &mut Frame, app: &App, area: Rect) {
+    let emulator = &app.emulator;
     let block = Block::default()
         .title(" Controls ")
         .borders(Borders::ALL)
@@ -423,74 +1966,221 @@ fn render_controls(f: &mut Frame, emulator: &Emulator, area: Rect) {
         "✗ None"
     };
 
-    let controls = vec![
-        Line::from(vec![
-            Span::styled("[S] ", Style::default().fg(Color::Yellow)),
-            Span::raw("Step Forward"),
-        ]),
-        Line::from(vec![
-            Span::styled("[C] ", Style::default().fg(Color::Yellow)),
-            Span::raw("Continue"),
-        ]),
-        Line::from(vec![
-            Span::styled("[R] ", Style::default().fg(Color::Yellow)),
-            Span::raw("Reset"),
-        ]),
+    let mut controls = vec![];
+
+    if let Some(fault) = &app.fault {
+        controls.push(Line::from(Span::styled(
+            format!("FAULT at 0x{:04X}: {}", emulator.pc, fault),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        )));
+        controls.push(Line::from(""));
+    }
+
+    if let Some(reason) = &app.stop {
+        let text = match reason {
+            StopReason::Breakpoint(addr) => match app.emulator.breakpoints.get(addr).and_then(|b| b.name.as_ref()) {
+                Some(name) => format!("Breakpoint {} at 0x{:04X}", name, addr),
+                None => format!("Breakpoint at 0x{:04X}", addr),
+            },
+            StopReason::Watchpoint(hit) => format!("WATCH {}", hit),
+            StopReason::Finished(RunUntil::Address(addr)) => format!("Reached 0x{:04X}", addr),
+            StopReason::Finished(RunUntil::Return { addr, .. }) => {
+                format!("Stepped over call, now at 0x{:04X}", addr)
+            }
+            StopReason::Finished(RunUntil::StackBelow(_)) => {
+                format!("Stepped out, now at 0x{:04X}", emulator.pc)
+            }
+        };
+        controls.push(Line::from(Span::styled(
+            text,
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+        )));
+        controls.push(Line::from(""));
+    }
+
+    if let Some(message) = app.current_message() {
+        controls.push(Line::from(Span::styled(
+            message.to_string(),
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        )));
+        controls.push(Line::from(""));
+    }
+
+    let rewind_label = format!("Rewind ({} frames)", app.rewind.len());
+    let slot_label = format!("Save/Load Slot {}", app.slot);
+
+    controls.extend([
+        key_hints(&[("S", "Step Forward"), ("C", "Continue")]),
+        key_hints(&[("O", "Step Over"), ("T", "Step Out")]),
+        key_hints(&[("H", "Run to Cursor"), ("F", "Advance Frame")]),
+        key_hints(&[("Z", "Step Back"), ("X", "Reverse Continue")]),
+        key_hints(&[("R", "Reset"), ("B", "Toggle Breakpoint")]),
+        key_hints(&[("↑/↓", "Select Line"), ("M", "Memory View")]),
         Line::from(""),
-        Line::from(vec![
-            Span::styled("[K] ", Style::default().fg(Color::Yellow)),
-            Span::raw("Save Checkpoint"),
-        ]),
-        Line::from(vec![
-            Span::styled("[L] ", Style::default().fg(Color::Yellow)),
-            Span::raw("Load Checkpoint"),
-        ]),
+        key_hints(&[("K", "Save Checkpoint"), ("L", "Load Checkpoint")]),
         Line::from(vec![
             Span::styled("    ", Style::default()),
             Span::styled(checkpoint_status, Style::default().fg(Color::Cyan)),
         ]),
+        key_hints(&[("Bksp", &rewind_label)]),
+        key_hints(&[("F5/F9", &slot_label), ("F6/F7", "Change Slot")]),
         Line::from(""),
-        Line::from(vec![
-            Span::styled("[I] ", Style::default().fg(Color::Yellow)),
-            Span::raw("Exit Inspector"),
-        ]),
-        Line::from(vec![
-            Span::styled("[ESC] ", Style::default().fg(Color::Yellow)),
-            Span::raw("Quit"),
-        ]),
-    ];
+        key_hints(&[("I", "Exit Inspector"), ("ESC", "Quit")]),
+    ]);
 
     let controls_widget = Paragraph::new(controls);
     f.render_widget(controls_widget, inner);
 }
 
-fn render_display_crt(display: &[bool; 2048], ghost: &[u8; 2048]) -> String {
-    let mut output = String::with_capacity(DISPLAY_HEIGHT * (DISPLAY_WIDTH * 2 + 1));
+fn render_command_line(f: &mut Frame, app: &App, area: Rect) {
+    let line = match &app.prompt {
+        Some(input) => {
+            let mut spans = vec![
+                Span::styled(":", Style::default().fg(Color::Yellow)),
+                Span::raw(input.clone()),
+                Span::styled("█", Style::default().fg(Color::Yellow)),
+            ];
+            if let Some(error) = &app.prompt_error {
+                spans.push(Span::styled(
+                    format!("  {}", error),
+                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                ));
+            }
+            Line::from(spans)
+        }
+        None => Line::from(Span::styled(
+            " [:] b ADDR [if COND] [hits N]  d ADDR|all  w START[..END] [rwx]  dw ADDR|all  s N  until ADDR  \
+             g ADDR  p EXPR  a INSTR  export [FILE]",
+            Style::default().fg(Color::DarkGray),
+        )),
+    };
+
+    f.render_widget(Paragraph::new(line), area);
+}
+
+/// A row of `[key] action` hints for the Controls panel.
+fn key_hints(hints: &[(&str, &str)]) -> Line<'static> {
+    let mut spans = Vec::with_capacity(hints.len() * 2);
+    for (key, action) in hints {
+        spans.push(Span::styled(format!("[{}] ", key), Style::default().fg(Color::Yellow)));
+        spans.push(Span::raw(format!("{:<20}", action)));
+    }
+    Line::from(spans)
+}
 
-    for y in 0..DISPLAY_HEIGHT {
-        for x in 0..DISPLAY_WIDTH {
-            let idx = y * DISPLAY_WIDTH + x;
-            let pixel = display[idx];
-            let ghost_val = ghost[idx];
+/// Colour for each combination of lit bitplanes. Plain CHIP-8 only ever
+/// uses plane 1, which keeps the classic green phosphor look.
+const PLANE_COLORS: [Color; 16] = [
+    Color::Reset,
+    Color::Green,
+    Color::Red,
+    Color::Yellow,
+    Color::Blue,
+    Color::Cyan,
+    Color::Magenta,
+    Color::White,
+    Color::DarkGray,
+    Color::LightGreen,
+    Color::LightRed,
+    Color::LightYellow,
+    Color::LightBlue,
+    Color::LightCyan,
+    Color::LightMagenta,
+    Color::Gray,
+];
+
+/// Display colours for a ROM: [`PLANE_COLORS`], with the first ones
+/// replaced by the colours its database entry gives, background first.
+fn palette(info: Option<&RomInfo>) -> [Color; 16] {
+    let mut palette = PLANE_COLORS;
+    if let Some(info) = info {
+        for (color, &(r, g, b)) in palette.iter_mut().zip(&info.colors) {
+            *color = Color::Rgb(r, g, b);
+        }
+    }
+    palette
+}
 
-            let char = if pixel {
-                if y % 2 == 0 {
-                    "██" 
+fn render_display_crt(emulator: &Emulator, palette: &[Color; 16]) -> Vec<Line<'static>> {
+    let (width, height) = (emulator.width(), emulator.height());
+    let display = &emulator.display;
+    let ghost = &emulator.ghost_display;
+    let background = Style::default().bg(palette[0]);
+    let ghost_style = background.fg(palette[1]);
+
+    let mut lines = Vec::with_capacity(height);
+
+    if !emulator.hires {
+        for y in 0..height {
+            let mut spans: Vec<Span<'static>> = Vec::new();
+            for x in 0..width {
+                let idx = y * width + x;
+                let pixel = display[idx];
+                let ghost_val = ghost[idx];
+
+                let (char, style) = if pixel != 0 {
+                    let style = background.fg(palette[pixel as usize & 0x0F]);
+                    if y % 2 == 0 {
+                        ("██", style)
+                    } else {
+                        ("▓▓", style)
+                    }
+                } else if ghost_val > 50 {
+                    ("░░", ghost_style)
                 } else {
-                    "▓▓" 
-                }
-            } else if ghost_val > 50 {
-                "░░" 
-            } else {
-                "  " 
+                    ("  ", background)
+                };
+
+                push_cell(&mut spans, char, style);
+            }
+            lines.push(Line::from(spans));
+        }
+
+        return lines;
+    }
+
+    // 128x64 does not fit at two columns per pixel, so pack two pixel rows
+    // into each text row with half blocks. This keeps the same footprint as
+    // the low resolution screen.
+    for y in (0..height).step_by(2) {
+        let mut spans: Vec<Span<'static>> = Vec::new();
+        for x in 0..width {
+            let top = display[y * width + x] & 0x0F;
+            let bottom = display[(y + 1) * width + x] & 0x0F;
+            let faded = ghost[y * width + x] > 50 || ghost[(y + 1) * width + x] > 50;
+
+            let (char, style) = match (top, bottom) {
+                (0, 0) if faded => ("░", ghost_style),
+                (0, 0) => (" ", background),
+                (0, _) => ("▄", background.fg(palette[bottom as usize])),
+                (_, 0) => ("▀", background.fg(palette[top as usize])),
+                _ if top == bottom => ("█", background.fg(palette[top as usize])),
+                _ => (
+                    "▀",
+                    Style::default()
+                        .fg(palette[top as usize])
+                        .bg(palette[bottom as usize]),
+                ),
             };
 
-            output.push_str(char);
+            push_cell(&mut spans, char, style);
         }
-        output.push('\n');
+        lines.push(Line::from(spans));
     }
 
-    output
+    lines
+}
+
+/// Appends a display cell, merging it into the previous span when the style
+/// matches so a row stays a handful of spans rather than one per pixel.
+fn push_cell(spans: &mut Vec<Span<'static>>, cell: &str, style: Style) {
+    if let Some(last) = spans.last_mut() {
+        if last.style == style {
+            last.content.to_mut().push_str(cell);
+            return;
+        }
+    }
+    spans.push(Span::styled(cell.to_string(), style));
 }
 
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
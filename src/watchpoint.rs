@@ -0,0 +1,188 @@
+use std::fmt;
+
+/// The kind of memory access a watchpoint reacts to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    Execute,
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Access::Read => write!(f, "read"),
+            Access::Write => write!(f, "write"),
+            Access::Execute => write!(f, "execute"),
+        }
+    }
+}
+
+/// Watches an inclusive range of addresses for reads, writes and/or
+/// instruction execution.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub start: u16,
+    pub end: u16,
+    pub read: bool,
+    pub write: bool,
+    pub execute: bool,
+}
+
+impl Watchpoint {
+    pub fn contains(&self, addr: usize) -> bool {
+        (self.start as usize..=self.end as usize).contains(&addr)
+    }
+
+    pub fn matches(&self, addr: usize, access: Access) -> bool {
+        let watched = match access {
+            Access::Read => self.read,
+            Access::Write => self.write,
+            Access::Execute => self.execute,
+        };
+        watched && self.contains(addr)
+    }
+
+    /// Access flags in `rwx` form, e.g. `-w-`.
+    pub fn mode(&self) -> String {
+        [(self.read, 'r'), (self.write, 'w'), (self.execute, 'x')]
+            .iter()
+            .map(|&(set, c)| if set { c } else { '-' })
+            .collect()
+    }
+
+    pub fn describe(&self) -> String {
+        if self.start == self.end {
+            format!("0x{:04X} {}", self.start, self.mode())
+        } else {
+            format!("0x{:04X}-0x{:04X} {}", self.start, self.end, self.mode())
+        }
+    }
+}
+
+/// A watched access: which instruction touched which byte, and the byte
+/// before and after. `old` and `new` are equal for reads, and zero for
+/// execution, where the instruction itself is `opcode`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WatchHit {
+    pub pc: u16,
+    pub opcode: u16,
+    pub addr: usize,
+    pub access: Access,
+    pub old: u8,
+    pub new: u8,
+}
+
+impl fmt::Display for WatchHit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.access {
+            Access::Write => write!(
+                f,
+                "0x{:04X} ({:04X}) wrote 0x{:04X}: {:02X} -> {:02X}",
+                self.pc, self.opcode, self.addr, self.old, self.new
+            ),
+            Access::Read => write!(
+                f,
+                "0x{:04X} ({:04X}) read 0x{:04X}: {:02X}",
+                self.pc, self.opcode, self.addr, self.old
+            ),
+            Access::Execute => write!(f, "executing 0x{:04X} ({:04X})", self.pc, self.opcode),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::{Emulator, StopReason};
+
+    fn watch(start: u16, end: u16, mode: &str) -> Watchpoint {
+        Watchpoint {
+            start,
+            end,
+            read: mode.contains('r'),
+            write: mode.contains('w'),
+            execute: mode.contains('x'),
+        }
+    }
+
+    /// Runs `rom` with one watchpoint until it stops, at most one frame.
+    fn run(rom: &[u8], watchpoint: Watchpoint) -> (Emulator, Option<WatchHit>) {
+        let mut emu = Emulator::new();
+        emu.load_rom(rom);
+        emu.watchpoints.push(watchpoint);
+        let hit = match emu.run_frame([false; 16]).unwrap().stop {
+            Some(StopReason::Watchpoint(hit)) => Some(hit),
+            _ => None,
+        };
+        (emu, hit)
+    }
+
+    /// `LD V0, 42`, `LD I, 0x300`, then `instruction`, then a loop.
+    fn program(instruction: [u8; 2]) -> Vec<u8> {
+        vec![0x60, 0x42, 0xA3, 0x00, instruction[0], instruction[1], 0x12, 0x06]
+    }
+
+    #[test]
+    fn reads_stop_after_the_instruction() {
+        let (emu, hit) = run(&program([0xF0, 0x65]), watch(0x300, 0x300, "r"));
+        let hit = hit.unwrap();
+        assert_eq!((hit.pc, hit.opcode, hit.addr, hit.access), (0x204, 0xF065, 0x300, Access::Read));
+        assert_eq!((hit.old, hit.new), (0, 0));
+        assert_eq!((emu.pc, emu.v[0]), (0x206, 0));
+        assert_eq!(hit.to_string(), "0x0204 (F065) read 0x0300: 00");
+    }
+
+    #[test]
+    fn writes_report_the_old_and_new_byte() {
+        let (emu, hit) = run(&program([0xF0, 0x55]), watch(0x300, 0x300, "w"));
+        let hit = hit.unwrap();
+        assert_eq!((hit.addr, hit.access, hit.old, hit.new), (0x300, Access::Write, 0x00, 0x42));
+        assert_eq!(emu.memory[0x300], 0x42);
+        assert_eq!(hit.to_string(), "0x0204 (F055) wrote 0x0300: 00 -> 42");
+    }
+
+    #[test]
+    fn ranges_report_the_first_watched_byte() {
+        let (_, hit) = run(&program([0xF3, 0x55]), watch(0x302, 0x310, "w"));
+        assert_eq!(hit.unwrap().addr, 0x302);
+
+        let (_, hit) = run(&program([0xF3, 0x55]), watch(0x304, 0x310, "w"));
+        assert_eq!(hit, None);
+    }
+
+    #[test]
+    fn other_kinds_of_access_are_ignored() {
+        let (_, hit) = run(&program([0xF0, 0x55]), watch(0x300, 0x300, "rx"));
+        assert_eq!(hit, None);
+        let (_, hit) = run(&program([0xF0, 0x65]), watch(0x300, 0x300, "wx"));
+        assert_eq!(hit, None);
+    }
+
+    #[test]
+    fn execution_stops_before_the_instruction() {
+        let (mut emu, hit) = run(&program([0xF0, 0x55]), watch(0x204, 0x205, "x"));
+        let hit = hit.unwrap();
+        assert_eq!((hit.pc, hit.opcode, hit.addr, hit.access), (0x204, 0xF055, 0x204, Access::Execute));
+        assert_eq!((hit.old, hit.new), (0, 0));
+        assert_eq!((emu.pc, emu.memory[0x300]), (0x204, 0));
+        assert_eq!(hit.to_string(), "executing 0x0204 (F055)");
+
+        // Resuming runs the watched instruction.
+        emu.run_frame([false; 16]).unwrap();
+        assert_eq!(emu.memory[0x300], 0x42);
+    }
+
+    #[test]
+    fn execution_of_the_first_instruction_is_caught() {
+        let (emu, hit) = run(&program([0xF0, 0x55]), watch(0x200, 0x200, "x"));
+        assert_eq!(hit.unwrap().pc, 0x200);
+        assert_eq!(emu.instruction_count, 0);
+    }
+
+    #[test]
+    fn descriptions() {
+        assert_eq!(watch(0x300, 0x300, "w").describe(), "0x0300 -w-");
+        assert_eq!(watch(0x300, 0x30F, "rx").describe(), "0x0300-0x030F r-x");
+    }
+}